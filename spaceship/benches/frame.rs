@@ -0,0 +1,65 @@
+// Frame time of the default 6-planet scene for each raster mode: the
+// fragment-collecting pipeline, the single-threaded streaming rasterizer
+// and the multithreaded tiled rasterizer.
+//
+// "collected" is today's `RasterMode::Collected`, not the renderer as it was
+// before streaming: it shares the current clipping and fixed-point triangle
+// setup, so the speedups only measure collecting fragments versus streaming.
+//
+// cargo bench --bench frame
+
+use std::time::{Duration, Instant};
+
+use spaceship::color::LinearColor;
+use spaceship::framebuffer::Framebuffer;
+use spaceship::postprocess::default_post_process;
+use spaceship::render::{create_frame_uniforms, Renderer, RasterMode};
+use spaceship::scene::{Scene, default_camera};
+use spaceship::tonemap::ToneMapping;
+
+const RESOLUTIONS: [(usize, usize); 2] = [(800, 600), (1920, 1440)];
+const WARMUP_FRAMES: usize = 10;
+const FRAMES: usize = 60;
+
+fn bench_mode(name: &str, mode: RasterMode, width: usize, height: usize) -> (Duration, Vec<LinearColor>) {
+    let mut scene = Scene::solar_system();
+    let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.set_background_color(0x000011);
+    let frame_uniforms = create_frame_uniforms(&default_camera(), width, height);
+    let post_process = default_post_process();
+
+    let mut total = Duration::ZERO;
+    for frame in 0..WARMUP_FRAMES + FRAMES {
+        let time = frame as f32 * 0.016;
+        scene.update(0.016);
+
+        let start = Instant::now();
+        framebuffer.clear();
+        scene.render(&mut framebuffer, &mut Renderer::new(mode), &frame_uniforms, time);
+        post_process.apply(&mut framebuffer, time);
+        framebuffer.resolve(&ToneMapping::default());
+        if frame >= WARMUP_FRAMES {
+            total += start.elapsed();
+        }
+    }
+
+    let per_frame = total / FRAMES as u32;
+    println!("  {:<10} {:>8.3} ms/frame", name, per_frame.as_secs_f64() * 1000.0);
+    (per_frame, framebuffer.color)
+}
+
+fn main() {
+    for (width, height) in RESOLUTIONS {
+        println!("Default scene, {}x{}, {} frames", width, height, FRAMES);
+
+        let (collected, collected_image) = bench_mode("collected", RasterMode::Collected, width, height);
+        let (streaming, streaming_image) = bench_mode("streaming", RasterMode::Streaming, width, height);
+        let (tiled, tiled_image) = bench_mode("tiled", RasterMode::tiled(), width, height);
+
+        assert_eq!(collected_image, streaming_image, "streaming path produced a different image");
+        assert_eq!(streaming_image, tiled_image, "tiled path produced a different image");
+
+        println!("  streaming speedup {:>6.2}x", collected.as_secs_f64() / streaming.as_secs_f64());
+        println!("  tiled speedup     {:>6.2}x", collected.as_secs_f64() / tiled.as_secs_f64());
+    }
+}
@@ -0,0 +1,64 @@
+use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
+
+pub struct Camera {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub up: Vec3,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    pub fn new(eye: Vec3, center: Vec3, up: Vec3, fov: f32, near: f32, far: f32) -> Self {
+        Camera {
+            eye,
+            center,
+            up,
+            fov,
+            near,
+            far,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        look_at(&self.eye, &self.center, &self.up)
+    }
+
+    pub fn projection_matrix(&self, aspect: f32) -> Mat4 {
+        perspective(aspect, self.fov, self.near, self.far)
+    }
+
+    // Rotate the eye around the center (yaw around the up axis, pitch towards it)
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        let offset = self.eye - self.center;
+        let radius = offset.magnitude();
+
+        let current_yaw = offset.z.atan2(offset.x);
+        let current_pitch = (offset.y / radius).asin();
+
+        let new_yaw = current_yaw + delta_yaw;
+        // Keep away from the poles so look_at never degenerates
+        let new_pitch = (current_pitch + delta_pitch).clamp(-1.5, 1.5);
+
+        self.eye = self.center + Vec3::new(
+            radius * new_pitch.cos() * new_yaw.cos(),
+            radius * new_pitch.sin(),
+            radius * new_pitch.cos() * new_yaw.sin(),
+        );
+    }
+
+    // Move the eye along the view direction, never closer than `min_distance`
+    pub fn zoom(&mut self, delta: f32, min_distance: f32) {
+        let offset = self.eye - self.center;
+        let distance = (offset.magnitude() - delta).max(min_distance);
+        self.eye = self.center + offset.normalize() * distance;
+    }
+
+    // Look at a new target keeping the current eye offset
+    pub fn focus(&mut self, target: Vec3) {
+        let offset = self.eye - self.center;
+        self.center = target;
+        self.eye = target + offset;
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::PathBuf;
+use crate::clock::MAX_WARP;
+use crate::framebuffer::SAMPLE_COUNTS;
+use crate::gravity::Integrator;
+use crate::headless::HeadlessOptions;
+use crate::image::ImageFormat;
+use crate::noise::DEFAULT_SEED;
+use crate::postprocess::default_post_process;
+use crate::render::RasterMode;
+use crate::tonemap::ToneMapping;
+
+pub const USAGE: &str = "\
+Usage: spaceship [OPTIONS]
+
+Options:
+  --interactive            Open a window (default)
+  --headless               Render to image files without a window
+  --resolution WxH         Window or image size in pixels [default: 800x600]
+  --scale FACTOR           Framebuffer size relative to the resolution [default: 1]
+  --fps N                  Target frames per second [default: 60]
+  --scene FILE             Scene description to load instead of the built-in system
+  --seed N                 Seed of the procedural planet noise [default: 1337]
+  --time SECONDS           Simulated time of the first frame [default: 0]
+  --time-scale FACTOR      Simulated seconds per real second, 0 to 1000 [default: 1]
+  --msaa 1|2|4|8           Coverage samples per pixel [default: 1]
+  --gravity leapfrog|rk4   Move bodies under mutual gravity instead of fixed orbits
+  --frames N               Frames to render (headless) [default: 1]
+  --output FILE.png|.ppm   Image to write (headless) [default: spaceship_rendered.png]
+  -h, --help               Print this message";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Interactive,
+    Headless,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub mode: Mode,
+    pub width: usize, // Window, or image in headless mode
+    pub height: usize,
+    pub scale: f32, // Framebuffer size relative to the window
+    pub fps: f32,
+    pub scene: Option<PathBuf>,
+    pub seed: u32,
+    pub start_time: f32,
+    pub time_scale: f32,
+    pub samples: usize,
+    pub gravity: Option<Integrator>, // N-body mode, instead of Keplerian orbits
+    pub frames: usize,
+    pub output: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            mode: Mode::Interactive,
+            width: 800,
+            height: 600,
+            scale: 1.0,
+            fps: 60.0,
+            scene: None,
+            seed: DEFAULT_SEED,
+            start_time: 0.0,
+            time_scale: 1.0,
+            samples: 1,
+            gravity: None,
+            frames: 1,
+            output: PathBuf::from("spaceship_rendered.png"),
+        }
+    }
+}
+
+impl Options {
+    // Size of the rendered image; the window stretches it to its own size
+    pub fn framebuffer_size(&self) -> (usize, usize) {
+        let scaled = |size: usize| ((size as f32 * self.scale).round() as usize).max(1);
+        (scaled(self.width), scaled(self.height))
+    }
+
+    // Real seconds per frame at the target rate
+    pub fn frame_time(&self) -> f32 {
+        1.0 / self.fps
+    }
+
+    pub fn headless(&self) -> HeadlessOptions {
+        let (width, height) = self.framebuffer_size();
+        HeadlessOptions {
+            width,
+            height,
+            frames: self.frames,
+            start_time: self.start_time,
+            frame_time: self.frame_time(),
+            time_scale: self.time_scale,
+            output: self.output.clone(),
+            raster_mode: RasterMode::tiled(),
+            samples: self.samples,
+            post_process: default_post_process(),
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+}
+
+// Parses the command line (without the program name)
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut headless_only = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interactive" => options.mode = Mode::Interactive,
+            "--headless" => options.mode = Mode::Headless,
+            "--resolution" => {
+                let value = next_value(&mut args, arg)?;
+                (options.width, options.height) = parse_resolution(value)
+                    .ok_or_else(|| format!("--resolution expects WIDTHxHEIGHT, got {}", value))?;
+            }
+            "--scale" => {
+                options.scale = parse_positive(next_value(&mut args, arg)?)
+                    .filter(|&scale| scale <= 4.0)
+                    .ok_or_else(|| "--scale expects a factor above 0 and at most 4".to_string())?;
+            }
+            "--fps" => {
+                options.fps = parse_positive(next_value(&mut args, arg)?)
+                    .ok_or_else(|| "--fps expects a positive number".to_string())?;
+            }
+            "--scene" => {
+                options.scene = Some(PathBuf::from(next_value(&mut args, arg)?));
+            }
+            "--seed" => {
+                options.seed = next_value(&mut args, arg)?
+                    .parse()
+                    .map_err(|_| "--seed expects a whole number".to_string())?;
+            }
+            "--time" => {
+                options.start_time = next_value(&mut args, arg)?
+                    .parse()
+                    .ok()
+                    .filter(|time: &f32| time.is_finite() && *time >= 0.0)
+                    .ok_or_else(|| "--time expects a number of seconds".to_string())?;
+            }
+            "--time-scale" => {
+                options.time_scale = next_value(&mut args, arg)?
+                    .parse()
+                    .ok()
+                    .filter(|scale: &f32| (0.0..=MAX_WARP).contains(scale))
+                    .ok_or_else(|| "--time-scale expects a factor from 0 to 1000".to_string())?;
+            }
+            "--msaa" => {
+                options.samples = next_value(&mut args, arg)?
+                    .parse()
+                    .ok()
+                    .filter(|samples| SAMPLE_COUNTS.contains(samples))
+                    .ok_or_else(|| "--msaa expects 1, 2, 4 or 8".to_string())?;
+            }
+            "--gravity" => {
+                options.gravity = Some(
+                    Integrator::from_name(next_value(&mut args, arg)?)
+                        .ok_or_else(|| "--gravity expects leapfrog or rk4".to_string())?,
+                );
+            }
+            "--frames" => {
+                options.frames = next_value(&mut args, arg)?
+                    .parse()
+                    .ok()
+                    .filter(|&frames| frames >= 1)
+                    .ok_or_else(|| "--frames expects a whole number of at least 1".to_string())?;
+                headless_only = Some(arg);
+            }
+            "--output" => {
+                options.output = PathBuf::from(next_value(&mut args, arg)?);
+                headless_only = Some(arg);
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    if let (Mode::Interactive, Some(flag)) = (options.mode, headless_only) {
+        return Err(format!("{} only applies to --headless", flag));
+    }
+    if ImageFormat::from_path(&options.output).is_none() {
+        return Err(format!("Unsupported output format: {}", options.output.display()));
+    }
+
+    Ok(options)
+}
+
+fn next_value<'a>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<&'a String, String> {
+    args.next().ok_or_else(|| format!("{} expects a value", flag))
+}
+
+fn parse_resolution(value: &str) -> Option<(usize, usize)> {
+    let (width, height) = value.split_once(['x', 'X'])?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+fn parse_positive(value: &str) -> Option<f32> {
+    value.parse().ok().filter(|value: &f32| value.is_finite() && *value > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn no_arguments_give_the_interactive_defaults() {
+        assert_eq!(parse(""), Ok(Options::default()));
+        assert_eq!(Options::default().framebuffer_size(), (800, 600));
+    }
+
+    #[test]
+    fn every_option_is_parsed() {
+        let options = parse(
+            "--headless --resolution 1280x720 --scale 0.5 --fps 30 --scene systems/binary.scene \
+             --seed 42 --time 12.5 --time-scale 100 --msaa 4 --gravity rk4 --frames 3 --output out.ppm",
+        )
+        .unwrap();
+
+        assert_eq!(options.mode, Mode::Headless);
+        assert_eq!((options.width, options.height), (1280, 720));
+        assert_eq!(options.framebuffer_size(), (640, 360));
+        assert_eq!(options.fps, 30.0);
+        assert_eq!(options.scene, Some(PathBuf::from("systems/binary.scene")));
+        assert_eq!(options.seed, 42);
+        assert_eq!((options.start_time, options.time_scale), (12.5, 100.0));
+        assert_eq!((options.samples, options.frames), (4, 3));
+        assert_eq!(options.gravity, Some(Integrator::Rk4));
+        assert_eq!(options.output, PathBuf::from("out.ppm"));
+
+        let headless = options.headless();
+        assert_eq!((headless.width, headless.height), (640, 360));
+        assert_eq!((headless.frame_time, headless.time_scale), (1.0 / 30.0, 100.0));
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        for (args, message) in [
+            ("--resolution 800", "--resolution expects WIDTHxHEIGHT, got 800"),
+            ("--resolution 0x600", "--resolution expects WIDTHxHEIGHT, got 0x600"),
+            ("--scale 0", "--scale expects a factor above 0 and at most 4"),
+            ("--fps -5", "--fps expects a positive number"),
+            ("--time-scale -1", "--time-scale expects a factor from 0 to 1000"),
+            ("--time-scale 5000", "--time-scale expects a factor from 0 to 1000"),
+            ("--msaa 3", "--msaa expects 1, 2, 4 or 8"),
+            ("--gravity euler", "--gravity expects leapfrog or rk4"),
+            ("--seed", "--seed expects a value"),
+            ("--headless --frames 0", "--frames expects a whole number of at least 1"),
+            ("--headless --frames two", "--frames expects a whole number of at least 1"),
+            ("--frames 10", "--frames only applies to --headless"),
+            ("--headless --output frame.jpg", "Unsupported output format: frame.jpg"),
+            ("--fullscreen", "Unknown argument: --fullscreen"),
+        ] {
+            assert_eq!(parse(args), Err(message.to_string()), "{}", args);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use nalgebra_glm::Vec4;
+use crate::vertex::Vertex;
+
+// Signed distance to each frustum plane in homogeneous clip space.
+// A vertex is inside the view volume when all six are >= 0:
+// -w <= x <= w, -w <= y <= w, -w <= z <= w
+const PLANES: [fn(&Vec4) -> f32; 6] = [
+    |p| p.w + p.x, // Left
+    |p| p.w - p.x, // Right
+    |p| p.w + p.y, // Bottom
+    |p| p.w - p.y, // Top
+    |p| p.w + p.z, // Near
+    |p| p.w - p.z, // Far
+];
+
+// Sutherland–Hodgman clipping of a triangle against the view frustum.
+// The clipped convex polygon is written to `polygon` (left empty if the
+// triangle is fully outside); `scratch` is reused between planes so no
+// allocation happens once both buffers have grown to their working size.
+pub fn clip_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, polygon: &mut Vec<Vertex>, scratch: &mut Vec<Vertex>) {
+    polygon.clear();
+    let (a, b, c) = (&v1.clip_position, &v2.clip_position, &v3.clip_position);
+
+    // Trivial reject: the whole triangle is outside a single plane
+    if PLANES.iter().any(|plane| plane(a) < 0.0 && plane(b) < 0.0 && plane(c) < 0.0) {
+        return;
+    }
+
+    polygon.push(v1.clone());
+    polygon.push(v2.clone());
+    polygon.push(v3.clone());
+
+    // Trivial accept: the whole triangle is inside every plane
+    if PLANES.iter().all(|plane| plane(a) >= 0.0 && plane(b) >= 0.0 && plane(c) >= 0.0) {
+        return;
+    }
+
+    for plane in PLANES.iter() {
+        if polygon.is_empty() {
+            break;
+        }
+
+        std::mem::swap(polygon, scratch);
+        polygon.clear();
+
+        for i in 0..scratch.len() {
+            let current = &scratch[i];
+            let next = &scratch[(i + 1) % scratch.len()];
+
+            let current_distance = plane(&current.clip_position);
+            let next_distance = plane(&next.clip_position);
+
+            if current_distance >= 0.0 {
+                polygon.push(current.clone());
+            }
+
+            // The edge crosses the plane: emit the intersection point
+            if (current_distance >= 0.0) != (next_distance >= 0.0) {
+                let t = current_distance / (current_distance - next_distance);
+                polygon.push(current.lerp(next, t));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
+
+    // Texture coordinates and world position mirror the clip position, so
+    // any vertex produced by clipping can be checked for consistent
+    // interpolation
+    fn vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::new(z, w));
+        vertex.clip_position = Vec4::new(x, y, z, w);
+        vertex
+    }
+
+    fn clip(v1: Vertex, v2: Vertex, v3: Vertex) -> Vec<Vertex> {
+        let (mut polygon, mut scratch) = (Vec::new(), Vec::new());
+        clip_triangle(&v1, &v2, &v3, &mut polygon, &mut scratch);
+        polygon
+    }
+
+    fn assert_inside(polygon: &[Vertex]) {
+        for vertex in polygon {
+            let p = vertex.clip_position;
+            assert!(PLANES.iter().all(|plane| plane(&p) >= -1e-5), "{:?} is outside", p);
+            assert!(p.w > 0.0);
+            assert!((vertex.tex_coords - Vec2::new(p.z, p.w)).norm() < 1e-5);
+            assert!((vertex.world_position - p.xyz()).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn triangle_outside_one_plane_is_rejected() {
+        let polygon = clip(vertex(2.0, 0.0, 0.0, 1.0), vertex(3.0, 1.0, 0.0, 1.0), vertex(2.5, -1.0, 0.5, 1.0));
+        assert!(polygon.is_empty());
+    }
+
+    #[test]
+    fn triangle_inside_is_kept_as_is() {
+        let polygon = clip(vertex(-0.5, -0.5, 0.0, 1.0), vertex(0.5, -0.5, 0.2, 1.0), vertex(0.0, 0.5, 0.4, 2.0));
+        let positions: Vec<Vec4> = polygon.iter().map(|vertex| vertex.clip_position).collect();
+        assert_eq!(positions, [Vec4::new(-0.5, -0.5, 0.0, 1.0), Vec4::new(0.5, -0.5, 0.2, 1.0), Vec4::new(0.0, 0.5, 0.4, 2.0)]);
+    }
+
+    #[test]
+    fn triangle_through_the_near_plane_loses_the_part_behind_it() {
+        // Only the first vertex is in front of the near plane: one vertex
+        // becomes two, both on the plane
+        let polygon = clip(vertex(0.0, 0.0, -1.5, 0.5), vertex(0.5, 0.0, 0.5, 2.0), vertex(-0.5, 0.5, 0.5, 2.0));
+        assert_eq!(polygon.len(), 4);
+        assert_inside(&polygon);
+        let on_near_plane = polygon.iter().filter(|vertex| (vertex.clip_position.w + vertex.clip_position.z).abs() < 1e-5);
+        assert_eq!(on_near_plane.count(), 2);
+
+        // A vertex behind the eye (w < 0) is outside the side planes too,
+        // yet what is left has positive w and can be divided safely
+        let polygon = clip(vertex(0.0, 0.0, 0.5, -1.0), vertex(0.5, 0.0, 0.5, 2.0), vertex(-0.5, 0.5, 0.5, 2.0));
+        assert!(polygon.len() >= 3);
+        assert_inside(&polygon);
+    }
+
+    #[test]
+    fn triangle_over_a_frustum_corner_gains_vertices() {
+        // The second vertex is past both the right and the top planes; the
+        // right plane turns it into two vertices, and the top plane turns
+        // the upper one of those into two more
+        let polygon = clip(vertex(0.0, -0.5, 0.0, 1.0), vertex(3.0, 3.0, 0.0, 1.0), vertex(-0.5, 0.0, 0.0, 1.0));
+        assert_eq!(polygon.len(), 5);
+        assert_inside(&polygon);
+        assert!(polygon.iter().any(|vertex| (vertex.clip_position - Vec4::new(1.0, 1.0, 0.0, 1.0)).norm() < 1e-5));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+
+// Fixed updates per real second, whatever the frame rate. Each update
+// advances the simulation by warp / UPDATE_RATE seconds, so speeding time
+// up makes the steps longer rather than more frequent.
+pub const UPDATE_RATE: f64 = 60.0;
+pub const MAX_WARP: f32 = 1000.0;
+
+// Stops offered by `faster` and `slower`
+pub const WARP_LEVELS: [f32; 14] = [0.0, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+// Simulated time, the single source of both scene updates and the shader
+// `time` uniform. Rendering reads `time()` and never advances the clock.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationClock {
+    time: f64,        // Simulated seconds
+    warp: f32,        // Simulated seconds per real second
+    paused: bool,
+    reversed: bool,
+    accumulator: f64, // Real seconds not yet turned into updates
+}
+
+impl SimulationClock {
+    pub fn new(time: f32, warp: f32) -> Self {
+        SimulationClock {
+            time: time as f64,
+            warp: warp.clamp(0.0, MAX_WARP),
+            paused: false,
+            reversed: false,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    pub fn warp(&self) -> f32 {
+        self.warp
+    }
+
+    pub fn set_warp(&mut self, warp: f32) {
+        self.warp = warp.clamp(0.0, MAX_WARP);
+    }
+
+    // The next warp level up, or down
+    pub fn faster(&mut self) {
+        if let Some(&warp) = WARP_LEVELS.iter().find(|&&level| level > self.warp) {
+            self.warp = warp;
+        }
+    }
+
+    pub fn slower(&mut self) {
+        if let Some(&warp) = WARP_LEVELS.iter().rev().find(|&&level| level < self.warp) {
+            self.warp = warp;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.accumulator = 0.0;
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    pub fn toggle_reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+
+    // Simulated seconds per update, negative when running backwards
+    pub fn update_step(&self) -> f32 {
+        let step = (self.warp as f64 / UPDATE_RATE) as f32;
+        if self.reversed { -step } else { step }
+    }
+
+    // Feeds `real_seconds` of wall-clock time and runs every whole update
+    // it covers; the remainder carries over to the next frame. Returns the
+    // number of updates.
+    pub fn tick(&mut self, real_seconds: f32, mut update: impl FnMut(f32)) -> usize {
+        if self.paused {
+            return 0;
+        }
+
+        // The tolerance keeps frames that are an exact multiple of the
+        // update interval from losing an update to rounding
+        let interval = 1.0 / UPDATE_RATE;
+        self.accumulator += real_seconds.max(0.0) as f64;
+        let mut updates = 0;
+        while self.accumulator + 1e-9 >= interval {
+            self.accumulator -= interval;
+            self.apply(self.update_step(), &mut update);
+            updates += 1;
+        }
+        updates
+    }
+
+    // A single update in the current direction, for stepping while paused
+    pub fn single_step(&mut self, mut update: impl FnMut(f32)) {
+        self.apply(self.update_step(), &mut update);
+    }
+
+    // Jumps to `time` in fixed updates of 1/UPDATE_RATE simulated seconds,
+    // whatever the warp, so the scene ends up in the same state for any
+    // time scale
+    pub fn seek(&mut self, time: f32, mut update: impl FnMut(f32)) {
+        let step = 1.0 / UPDATE_RATE;
+        let mut remaining = time as f64 - self.time;
+        while remaining.abs() > step + 1e-9 {
+            let dt = step.copysign(remaining);
+            self.apply(dt as f32, &mut update);
+            remaining -= dt;
+        }
+        self.apply(remaining as f32, &mut update);
+    }
+
+    fn apply(&mut self, dt: f32, update: &mut impl FnMut(f32)) {
+        if dt != 0.0 {
+            update(dt);
+            self.time += dt as f64;
+        }
+    }
+}
+
+impl fmt::Display for SimulationClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "t = {:.2}s, {}x", self.time, self.warp)?;
+        if self.reversed {
+            write!(f, ", reversed")?;
+        }
+        if self.paused {
+            write!(f, ", paused")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+
+    #[test]
+    fn updates_run_at_a_fixed_rate_whatever_the_frame_rate() {
+        for frame_time in [1.0 / 30.0, 1.0 / 60.0, 1.0 / 144.0, 0.05] {
+            let mut clock = SimulationClock::new(0.0, 10.0);
+            let mut steps = Vec::new();
+            let frames = (1.0 / frame_time as f64).round() as usize;
+            let updates: usize = (0..frames).map(|_| clock.tick(frame_time, |dt| steps.push(dt))).sum();
+
+            // One real second is 60 updates of 10/60 simulated seconds
+            assert_eq!(updates, 60, "frame time {}", frame_time);
+            assert!(steps.iter().all(|&dt| (dt - 10.0 / 60.0).abs() < 1e-6));
+            assert!((clock.time() - 10.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn paused_clock_only_moves_by_single_steps() {
+        let mut clock = SimulationClock::new(5.0, 1.0);
+        clock.toggle_pause();
+        assert_eq!(clock.tick(1.0, |_| panic!("updated while paused")), 0);
+        assert_eq!(clock.time(), 5.0);
+
+        let mut updates = 0;
+        clock.single_step(|_| updates += 1);
+        assert_eq!(updates, 1);
+        assert!((clock.time() - (5.0 + 1.0 / 60.0)).abs() < 1e-6);
+        assert_eq!(clock.to_string(), "t = 5.02s, 1x, paused");
+    }
+
+    #[test]
+    fn warp_is_limited_and_moves_through_levels() {
+        let mut clock = SimulationClock::new(0.0, 5000.0);
+        assert_eq!(clock.warp(), MAX_WARP);
+        clock.faster();
+        assert_eq!(clock.warp(), MAX_WARP);
+
+        clock.set_warp(3.0);
+        clock.faster();
+        assert_eq!(clock.warp(), 5.0);
+        clock.slower();
+        clock.slower();
+        assert_eq!(clock.warp(), 1.0);
+
+        clock.set_warp(0.0);
+        clock.slower();
+        assert_eq!(clock.warp(), 0.0);
+        assert_eq!(clock.tick(1.0, |_| panic!("updated at 0x")), 60);
+        assert_eq!(clock.time(), 0.0);
+    }
+
+    #[test]
+    fn seeking_takes_the_same_fixed_steps_at_any_warp() {
+        let seek = |warp: f32| {
+            let mut clock = SimulationClock::new(0.0, warp);
+            let mut steps = Vec::new();
+            clock.seek(2.0, |dt| steps.push(dt));
+            assert!((clock.time() - 2.0).abs() < 1e-5);
+            steps
+        };
+
+        let steps = seek(0.0);
+        assert_eq!(steps.len(), 120);
+        assert!(steps.iter().all(|&dt| dt > 0.0 && dt <= 1.0 / 60.0 + 1e-6));
+        assert_eq!(seek(1000.0), steps);
+    }
+
+    #[test]
+    fn reversing_brings_the_scene_back() {
+        let mut scene = Scene::solar_system();
+        let start: Vec<_> = scene.nodes().iter().map(|node| node.world_position()).collect();
+
+        let mut clock = SimulationClock::new(0.0, 20.0);
+        for _ in 0..90 {
+            clock.tick(1.0 / 60.0, |dt| scene.update(dt));
+        }
+        assert!((clock.time() - 30.0).abs() < 1e-4);
+        let moved = scene.nodes().iter().zip(&start).any(|(node, start)| (node.world_position() - start).norm() > 1.0);
+        assert!(moved);
+
+        clock.toggle_reverse();
+        for _ in 0..90 {
+            clock.tick(1.0 / 60.0, |dt| scene.update(dt));
+        }
+        assert!(clock.time().abs() < 1e-3, "{}", clock);
+        for (node, start) in scene.nodes().iter().zip(&start) {
+            assert!((node.world_position() - start).norm() < 0.05, "{} at {:?}", node.name, node.world_position());
+        }
+    }
+}
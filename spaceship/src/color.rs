@@ -1,4 +1,4 @@
-use std::ops::Mul;
+use std::ops::{Add, Mul};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
@@ -20,7 +20,7 @@ impl Color {
         Color { r: 255, g: 255, b: 255 }
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
@@ -44,3 +44,104 @@ impl Mul<f32> for Color {
         }
     }
 }
+
+// Linear-light RGB with float channels. Not clamped above 1.0, so emissive
+// surfaces like the star can be brighter than white until tone mapping.
+// `a` is the coverage used by the blend modes (1.0 = opaque); the
+// arithmetic operators work on the color and keep the left operand's alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl LinearColor {
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
+        LinearColor { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        LinearColor { r, g, b, a }
+    }
+
+    pub const fn with_alpha(self, a: f32) -> Self {
+        LinearColor { a, ..self }
+    }
+
+    pub const fn black() -> Self {
+        LinearColor::new(0.0, 0.0, 0.0)
+    }
+
+    // Decodes sRGB components on the usual 0-255 scale. Values above 255
+    // are extrapolated instead of clipped, giving HDR colors.
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Self {
+        LinearColor::new(
+            srgb_to_linear(r / 255.0),
+            srgb_to_linear(g / 255.0),
+            srgb_to_linear(b / 255.0),
+        )
+    }
+
+    // Relative luminance (Rec. 709 primaries)
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+}
+
+impl Default for LinearColor {
+    fn default() -> Self {
+        LinearColor::black()
+    }
+}
+
+impl From<Color> for LinearColor {
+    fn from(color: Color) -> Self {
+        LinearColor::from_srgb(color.r as f32, color.g as f32, color.b as f32)
+    }
+}
+
+impl Add for LinearColor {
+    type Output = LinearColor;
+
+    fn add(self, other: LinearColor) -> Self::Output {
+        LinearColor::rgba(self.r + other.r, self.g + other.g, self.b + other.b, self.a)
+    }
+}
+
+impl Mul<f32> for LinearColor {
+    type Output = LinearColor;
+
+    fn mul(self, scalar: f32) -> Self::Output {
+        LinearColor::rgba(self.r * scalar, self.g * scalar, self.b * scalar, self.a)
+    }
+}
+
+// Component-wise product, e.g. albedo times light color
+impl Mul for LinearColor {
+    type Output = LinearColor;
+
+    fn mul(self, other: LinearColor) -> Self::Output {
+        LinearColor::rgba(self.r * other.r, self.g * other.g, self.b * other.b, self.a)
+    }
+}
+
+// sRGB transfer functions on [0, 1] (the decode side also accepts values above 1)
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let value = value.max(0.0);
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -1,10 +1,70 @@
+use crate::color::{Color, LinearColor};
+use crate::tonemap::ToneMapping;
+
+// How a fragment is combined with the color already in the framebuffer.
+// Only opaque fragments write depth, so translucent surfaces never hide
+// what is drawn after them.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum BlendMode {
+    #[default]
+    Opaque,   // Replaces the pixel
+    Alpha,    // "Over" by the fragment's alpha (rings, clouds)
+    Additive, // Adds light scaled by alpha (glows, atmospheres)
+    Multiply, // Filters the pixel by the fragment's color (dust, haze)
+}
+
+impl BlendMode {
+    pub fn writes_depth(self) -> bool {
+        self == BlendMode::Opaque
+    }
+
+    // The framebuffer keeps its own alpha
+    pub fn blend(self, destination: LinearColor, source: LinearColor) -> LinearColor {
+        let alpha = source.a.clamp(0.0, 1.0);
+        match self {
+            BlendMode::Opaque => source.with_alpha(destination.a),
+            BlendMode::Alpha => destination * (1.0 - alpha) + source * alpha,
+            BlendMode::Additive => destination + source * alpha,
+            BlendMode::Multiply => destination * (LinearColor::new(1.0, 1.0, 1.0) * (1.0 - alpha) + source * alpha),
+        }
+    }
+}
+
+// Supported multisample counts
+pub const SAMPLE_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+// Sample offsets from the pixel center (standard D3D patterns, in pixels)
+pub fn sample_pattern(samples: usize) -> &'static [(f32, f32)] {
+    const X1: [(f32, f32); 1] = [(0.0, 0.0)];
+    const X2: [(f32, f32); 2] = [(0.25, 0.25), (-0.25, -0.25)];
+    const X4: [(f32, f32); 4] = [(-0.125, -0.375), (0.375, -0.125), (-0.375, 0.125), (0.125, 0.375)];
+    const X8: [(f32, f32); 8] = [
+        (0.0625, -0.1875), (-0.0625, 0.1875), (0.3125, 0.0625), (-0.1875, -0.3125),
+        (-0.3125, 0.3125), (-0.4375, -0.0625), (0.1875, 0.4375), (0.4375, -0.4375),
+    ];
+
+    match samples {
+        2 => &X2,
+        4 => &X4,
+        8 => &X8,
+        _ => &X1,
+    }
+}
+
+// Bodies are rendered into the linear HDR `color` target; `resolve` tone
+// maps it into the displayable 0xRRGGBB `buffer`. With multisampling the
+// rasterizer writes to per-sample buffers instead, and `resolve_samples`
+// averages them into `color` and `zbuffer`.
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    pub color: Vec<LinearColor>,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    samples: usize,
+    sample_color: Vec<LinearColor>, // Empty without multisampling
+    sample_depth: Vec<f32>,
     background_color: u32,
-    current_color: u32,
 }
 
 impl Framebuffer {
@@ -12,37 +72,158 @@ impl Framebuffer {
         Framebuffer {
             width,
             height,
+            color: vec![LinearColor::black(); width * height],
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            samples: 1,
+            sample_color: Vec::new(),
+            sample_depth: Vec::new(),
             background_color: 0x000000,
-            current_color: 0xFFFFFF,
         }
     }
 
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // Coverage samples per pixel, one of `SAMPLE_COUNTS`; takes effect on
+    // the next `clear`
+    pub fn set_samples(&mut self, samples: usize) {
+        assert!(SAMPLE_COUNTS.contains(&samples), "unsupported sample count {}", samples);
+        self.samples = samples;
+
+        let size = if samples > 1 { self.width * self.height * samples } else { 0 };
+        self.sample_color = vec![LinearColor::black(); size];
+        self.sample_depth = vec![f32::INFINITY; size];
+    }
+
     pub fn clear(&mut self) {
+        let background = LinearColor::from(Color::from_hex(self.background_color));
+        for pixel in self.color.iter_mut().chain(self.sample_color.iter_mut()) {
+            *pixel = background;
+        }
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
         }
-        for depth in self.zbuffer.iter_mut() {
+        for depth in self.zbuffer.iter_mut().chain(self.sample_depth.iter_mut()) {
             *depth = f32::INFINITY;
         }
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+    // Depth-tested write of a shaded fragment covering the whole pixel.
+    // `full_tile` only borrows the buffers, so this doesn't allocate.
+    pub fn fragment(&mut self, x: usize, y: usize, color: LinearColor, depth: f32) {
+        let mut tile = self.full_tile();
+        for sample in 0..tile.samples {
+            if tile.depth_test(x, y, sample, depth) {
+                tile.write_sample(x, y, sample, color, depth, BlendMode::Opaque);
             }
         }
     }
 
+    // Averages the samples of every pixel into `color` and keeps the
+    // nearest one in `zbuffer`; a no-op without multisampling
+    pub fn resolve_samples(&mut self) {
+        if self.samples == 1 {
+            return;
+        }
+
+        let weight = 1.0 / self.samples as f32;
+        let pixels = self.sample_color.chunks_exact(self.samples).zip(self.sample_depth.chunks_exact(self.samples));
+        for ((color, depth), (samples, sample_depths)) in self.color.iter_mut().zip(self.zbuffer.iter_mut()).zip(pixels) {
+            let sum = samples.iter().fold(LinearColor::black(), |sum, &sample| sum + sample);
+            *color = sum * weight;
+            *depth = sample_depths.iter().copied().fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    // Tone maps the HDR target into the displayable `buffer`
+    pub fn resolve(&mut self, tone_mapping: &ToneMapping) {
+        for (pixel, color) in self.buffer.iter_mut().zip(&self.color) {
+            *pixel = tone_mapping.map(*color).to_hex();
+        }
+    }
+
+    // The whole framebuffer as a single rasterization target
+    pub fn full_tile(&mut self) -> Tile<'_> {
+        let (color, zbuffer) = if self.samples > 1 {
+            (&mut self.sample_color, &mut self.sample_depth)
+        } else {
+            (&mut self.color, &mut self.zbuffer)
+        };
+        Tile { y_start: 0, width: self.width, height: self.height, samples: self.samples, color, zbuffer }
+    }
+
+    // Split the framebuffer into horizontal tiles of `tile_height` rows.
+    // Each tile borrows its own contiguous slice of the color and depth
+    // buffers, so tiles can be rasterized on different threads.
+    pub fn tiles(&mut self, tile_height: usize) -> Vec<Tile<'_>> {
+        let width = self.width;
+        let samples = self.samples;
+        let rows = tile_height.max(1);
+        let row_size = (width * samples).max(1);
+
+        let (color, zbuffer) = if samples > 1 {
+            (&mut self.sample_color, &mut self.sample_depth)
+        } else {
+            (&mut self.color, &mut self.zbuffer)
+        };
+
+        color
+            .chunks_mut(row_size * rows)
+            .zip(zbuffer.chunks_mut(row_size * rows))
+            .enumerate()
+            .map(|(i, (color, zbuffer))| Tile {
+                y_start: i * rows,
+                width,
+                height: color.len() / row_size,
+                samples,
+                color,
+                zbuffer,
+            })
+            .collect()
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
+}
 
-    pub fn set_current_color(&mut self, color: u32) {
-        self.current_color = color;
+// A band of full-width rows of a framebuffer. Coordinates passed to its
+// methods are framebuffer coordinates.
+pub struct Tile<'a> {
+    pub y_start: usize,
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    color: &'a mut [LinearColor],
+    zbuffer: &'a mut [f32],
+}
+
+impl Tile<'_> {
+    pub fn y_end(&self) -> usize {
+        self.y_start + self.height
+    }
+
+    fn index(&self, x: usize, y: usize, sample: usize) -> Option<usize> {
+        if x < self.width && (self.y_start..self.y_end()).contains(&y) && sample < self.samples {
+            Some(((y - self.y_start) * self.width + x) * self.samples + sample)
+        } else {
+            None
+        }
+    }
+
+    // True when a fragment at this depth would be visible in the sample
+    pub fn depth_test(&self, x: usize, y: usize, sample: usize, depth: f32) -> bool {
+        self.index(x, y, sample).is_some_and(|index| self.zbuffer[index] > depth)
+    }
+
+    pub fn write_sample(&mut self, x: usize, y: usize, sample: usize, color: LinearColor, depth: f32, blend_mode: BlendMode) {
+        if let Some(index) = self.index(x, y, sample) {
+            self.color[index] = blend_mode.blend(self.color[index], color);
+            if blend_mode.writes_depth() {
+                self.zbuffer[index] = depth;
+            }
+        }
     }
 }
@@ -0,0 +1,325 @@
+use nalgebra_glm::DVec3;
+use std::fmt;
+use crate::scene::NodeId;
+
+// How the N-body state is advanced. Leapfrog (kick-drift-kick velocity
+// Verlet) is symplectic and time-reversible, so energy errors stay bounded
+// over long runs; RK4 is more accurate per step but slowly drifts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    #[default]
+    Leapfrog,
+    Rk4,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 2] = [Integrator::Leapfrog, Integrator::Rk4];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Leapfrog => "leapfrog",
+            Integrator::Rk4 => "rk4",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Integrator::ALL.into_iter().find(|integrator| integrator.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gravity {
+    pub constant: f64,  // G, in world units³ per mass unit per second²
+    pub softening: f64, // Plummer length; keeps close passes finite
+    pub max_step: f64,  // Longest integration step, in simulated seconds
+    pub integrator: Integrator,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Gravity { constant: 1.0, softening: 0.5, max_step: 1.0 / 240.0, integrator: Integrator::Leapfrog }
+    }
+}
+
+// A point mass standing in for a scene node, in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub node: NodeId,
+    pub mass: f64,
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+// Conserved quantities, and how far they have moved since the simulation
+// started. Drifts are relative, so they read the same for any units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Diagnostics {
+    pub energy: f64,
+    pub momentum: DVec3,
+    pub angular_momentum: DVec3,
+    pub energy_drift: f64,           // (E - E₀) / |E₀|
+    pub momentum_drift: f64,         // |P - P₀| / Σ m·|v₀|
+    pub angular_momentum_drift: f64, // |L - L₀| / |L₀|
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "energy {:.6e} (drift {:+.2e}), momentum drift {:.2e}, angular momentum drift {:.2e}",
+            self.energy, self.energy_drift, self.momentum_drift, self.angular_momentum_drift
+        )
+    }
+}
+
+// Bodies moving under their mutual gravity
+pub struct NBody {
+    pub gravity: Gravity,
+    particles: Vec<Particle>,
+    accelerations: Vec<DVec3>, // For the current positions
+    time: f64,                 // Simulated seconds since the start
+    initial_energy: f64,
+    initial_momentum: DVec3,
+    initial_angular_momentum: DVec3,
+    momentum_scale: f64,
+}
+
+impl NBody {
+    pub fn new(gravity: Gravity, particles: Vec<Particle>) -> Self {
+        let mut system = NBody {
+            gravity,
+            particles,
+            accelerations: Vec::new(),
+            time: 0.0,
+            initial_energy: 0.0,
+            initial_momentum: DVec3::zeros(),
+            initial_angular_momentum: DVec3::zeros(),
+            momentum_scale: 0.0,
+        };
+        system.accelerations = system.accelerations_at(&system.positions());
+        system.initial_energy = system.energy();
+        system.initial_momentum = system.momentum();
+        system.initial_angular_momentum = system.angular_momentum();
+        system.momentum_scale = system.particles.iter().map(|p| p.mass * p.velocity.norm()).sum();
+        system
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn particle(&self, node: NodeId) -> Option<&Particle> {
+        self.particles.iter().find(|particle| particle.node == node)
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    // Advances by `dt` simulated seconds, negative to run backwards, in
+    // equal steps no longer than `max_step`
+    pub fn step(&mut self, dt: f64) {
+        if dt == 0.0 || self.particles.is_empty() {
+            return;
+        }
+        let steps = (dt.abs() / self.gravity.max_step).ceil().max(1.0) as usize;
+        let h = dt / steps as f64;
+        for _ in 0..steps {
+            match self.gravity.integrator {
+                Integrator::Leapfrog => self.leapfrog_step(h),
+                Integrator::Rk4 => self.rk4_step(h),
+            }
+        }
+        self.time += dt;
+    }
+
+    // Kick half a step, drift a full step, kick again with the new forces
+    fn leapfrog_step(&mut self, h: f64) {
+        for (particle, acceleration) in self.particles.iter_mut().zip(&self.accelerations) {
+            particle.velocity += acceleration * (h / 2.0);
+            particle.position += particle.velocity * h;
+        }
+        self.accelerations = self.accelerations_at(&self.positions());
+        for (particle, acceleration) in self.particles.iter_mut().zip(&self.accelerations) {
+            particle.velocity += acceleration * (h / 2.0);
+        }
+    }
+
+    // Classic fourth-order Runge-Kutta on positions and velocities
+    fn rk4_step(&mut self, h: f64) {
+        let x0 = self.positions();
+        let v0: Vec<DVec3> = self.particles.iter().map(|p| p.velocity).collect();
+        let offset = |base: &[DVec3], delta: &[DVec3], scale: f64| -> Vec<DVec3> {
+            base.iter().zip(delta).map(|(b, d)| b + d * scale).collect()
+        };
+
+        let a1 = self.accelerations.clone();
+        let x2 = offset(&x0, &v0, h / 2.0);
+        let v2 = offset(&v0, &a1, h / 2.0);
+        let a2 = self.accelerations_at(&x2);
+        let x3 = offset(&x0, &v2, h / 2.0);
+        let v3 = offset(&v0, &a2, h / 2.0);
+        let a3 = self.accelerations_at(&x3);
+        let x4 = offset(&x0, &v3, h);
+        let v4 = offset(&v0, &a3, h);
+        let a4 = self.accelerations_at(&x4);
+
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            particle.position = x0[i] + (v0[i] + 2.0 * v2[i] + 2.0 * v3[i] + v4[i]) * (h / 6.0);
+            particle.velocity = v0[i] + (a1[i] + 2.0 * a2[i] + 2.0 * a3[i] + a4[i]) * (h / 6.0);
+        }
+        self.accelerations = self.accelerations_at(&self.positions());
+    }
+
+    fn positions(&self) -> Vec<DVec3> {
+        self.particles.iter().map(|particle| particle.position).collect()
+    }
+
+    // Softened pairwise gravity, each pair visited once
+    pub fn accelerations_at(&self, positions: &[DVec3]) -> Vec<DVec3> {
+        let softening = self.gravity.softening * self.gravity.softening;
+        let mut accelerations = vec![DVec3::zeros(); positions.len()];
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                let offset = positions[j] - positions[i];
+                let distance_squared = offset.norm_squared() + softening;
+                let pull = offset * (self.gravity.constant / (distance_squared * distance_squared.sqrt()));
+                accelerations[i] += pull * self.particles[j].mass;
+                accelerations[j] -= pull * self.particles[i].mass;
+            }
+        }
+        accelerations
+    }
+
+    // Current acceleration of each particle, in particle order
+    pub fn accelerations(&self) -> &[DVec3] {
+        &self.accelerations
+    }
+
+    // Kinetic plus (softened) potential energy
+    pub fn energy(&self) -> f64 {
+        let softening = self.gravity.softening * self.gravity.softening;
+        let kinetic: f64 = self.particles.iter().map(|p| 0.5 * p.mass * p.velocity.norm_squared()).sum();
+        let mut potential = 0.0;
+        for (i, a) in self.particles.iter().enumerate() {
+            for b in &self.particles[i + 1..] {
+                let distance = ((b.position - a.position).norm_squared() + softening).sqrt();
+                potential -= self.gravity.constant * a.mass * b.mass / distance;
+            }
+        }
+        kinetic + potential
+    }
+
+    pub fn momentum(&self) -> DVec3 {
+        self.particles.iter().map(|p| p.velocity * p.mass).sum()
+    }
+
+    // About the world origin
+    pub fn angular_momentum(&self) -> DVec3 {
+        self.particles.iter().map(|p| p.position.cross(&p.velocity) * p.mass).sum()
+    }
+
+    pub fn diagnostics(&self) -> Diagnostics {
+        let energy = self.energy();
+        let momentum = self.momentum();
+        let angular_momentum = self.angular_momentum();
+        let relative = |change: f64, scale: f64| if scale > 0.0 { change / scale } else { change };
+
+        Diagnostics {
+            energy,
+            momentum,
+            angular_momentum,
+            energy_drift: relative(energy - self.initial_energy, self.initial_energy.abs()),
+            momentum_drift: relative((momentum - self.initial_momentum).norm(), self.momentum_scale),
+            angular_momentum_drift: relative(
+                (angular_momentum - self.initial_angular_momentum).norm(),
+                self.initial_angular_momentum.norm(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    // A light planet on a circular orbit of radius 100 around a heavy star,
+    // both moving about their common center of mass
+    fn star_and_planet(integrator: Integrator) -> NBody {
+        let (star_mass, planet_mass, radius): (f64, f64, f64) = (1.0e6, 10.0, 100.0);
+        let total = star_mass + planet_mass;
+        let speed = (total / radius).sqrt();
+        let gravity = Gravity { softening: 0.0, integrator, ..Gravity::default() };
+
+        NBody::new(gravity, vec![
+            Particle {
+                node: 0,
+                mass: star_mass,
+                position: DVec3::new(-radius * planet_mass / total, 0.0, 0.0),
+                velocity: DVec3::new(0.0, 0.0, -speed * planet_mass / total),
+            },
+            Particle {
+                node: 1,
+                mass: planet_mass,
+                position: DVec3::new(radius * star_mass / total, 0.0, 0.0),
+                velocity: DVec3::new(0.0, 0.0, speed * star_mass / total),
+            },
+        ])
+    }
+
+    fn period() -> f64 {
+        2.0 * PI * (100.0f64.powi(3) / (1.0e6 + 10.0)).sqrt()
+    }
+
+    #[test]
+    fn circular_orbit_closes_after_one_period() {
+        for integrator in Integrator::ALL {
+            let mut system = star_and_planet(integrator);
+            let start = system.particle(1).unwrap().position;
+            system.step(period() / 2.0);
+            assert!((system.particle(1).unwrap().position + start).norm() < 1e-2, "{:?}", integrator);
+            system.step(period() / 2.0);
+            assert!((system.particle(1).unwrap().position - start).norm() < 1e-2, "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn leapfrog_keeps_energy_and_momentum_over_many_orbits() {
+        // An eccentric orbit, which is harder on the integrator
+        let mut system = star_and_planet(Integrator::Leapfrog);
+        system.particles[1].velocity *= 0.7;
+        system = NBody::new(system.gravity, system.particles.clone());
+
+        for _ in 0..20 {
+            system.step(period());
+            let diagnostics = system.diagnostics();
+            assert!(diagnostics.energy_drift.abs() < 1e-4, "{}", diagnostics);
+            assert!(diagnostics.momentum_drift < 1e-12, "{}", diagnostics);
+            assert!(diagnostics.angular_momentum_drift < 1e-9, "{}", diagnostics);
+        }
+    }
+
+    #[test]
+    fn leapfrog_runs_backwards_to_where_it_started() {
+        let mut system = star_and_planet(Integrator::Leapfrog);
+        system.particles[1].velocity *= 0.8;
+        let start = system.particles.clone();
+
+        system.step(7.3);
+        system.step(-7.3);
+        for (particle, start) in system.particles().iter().zip(&start) {
+            assert!((particle.position - start.position).norm() < 1e-8);
+            assert!((particle.velocity - start.velocity).norm() < 1e-8);
+        }
+        assert!(system.time().abs() < 1e-12);
+    }
+
+    #[test]
+    fn integrators_are_named() {
+        for integrator in Integrator::ALL {
+            assert_eq!(Integrator::from_name(integrator.name()), Some(integrator));
+        }
+        assert_eq!(Integrator::from_name("euler"), None);
+    }
+}
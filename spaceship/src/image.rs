@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+}
+
+impl ImageFormat {
+    // Picks the format from the file extension (.ppm or .png)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+// Writes the color buffer to `path`, in the format given by its extension
+pub fn save_framebuffer(framebuffer: &Framebuffer, path: &Path) -> io::Result<()> {
+    let format = ImageFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image format: {} (expected .ppm or .png)", path.display()),
+        )
+    })?;
+
+    let writer = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Ppm => write_ppm(framebuffer, writer),
+        ImageFormat::Png => write_png(framebuffer, writer),
+    }
+}
+
+// Binary PPM (P6)
+pub fn write_ppm(framebuffer: &Framebuffer, mut writer: impl Write) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", framebuffer.width, framebuffer.height)?;
+    writer.write_all(&to_rgb(framebuffer))?;
+    writer.flush()
+}
+
+// 8-bit RGB PNG
+pub fn write_png(framebuffer: &Framebuffer, writer: impl Write) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, framebuffer.width as u32, framebuffer.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+    png_writer.write_image_data(&to_rgb(framebuffer)).map_err(io::Error::other)?;
+    png_writer.finish().map_err(io::Error::other)
+}
+
+// Reads an 8-bit RGB or RGBA PNG into a framebuffer (alpha is dropped)
+pub fn load_png(path: &Path) -> io::Result<Framebuffer> {
+    let decoder = png::Decoder::new(File::open(path)?);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut bytes).map_err(io::Error::other)?;
+
+    let channels = match (info.color_type, info.bit_depth) {
+        (png::ColorType::Rgb, png::BitDepth::Eight) => 3,
+        (png::ColorType::Rgba, png::BitDepth::Eight) => 4,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: only 8-bit RGB/RGBA PNGs are supported", path.display()),
+            ))
+        }
+    };
+
+    let mut framebuffer = Framebuffer::new(info.width as usize, info.height as usize);
+    framebuffer.buffer = bytes[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|p| ((p[0] as u32) << 16) | ((p[1] as u32) << 8) | p[2] as u32)
+        .collect();
+    Ok(framebuffer)
+}
+
+// 0xRRGGBB pixels -> packed RGB bytes
+fn to_rgb(framebuffer: &Framebuffer) -> Vec<u8> {
+    framebuffer
+        .buffer
+        .iter()
+        .flat_map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_contains_header_and_rgb_pixels() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.buffer = vec![0x112233, 0xAABBCC];
+
+        let mut bytes = Vec::new();
+        write_ppm(&framebuffer, &mut bytes).unwrap();
+
+        assert_eq!(bytes, b"P6\n2 1\n255\n\x11\x22\x33\xAA\xBB\xCC");
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.buffer = vec![0x000000, 0xFF0000, 0x00FF00, 0x0000FF, 0x123456, 0xFFFFFF];
+
+        let path = std::env::temp_dir().join(format!("spaceship_round_trip_{}.png", std::process::id()));
+        save_framebuffer(&framebuffer, &path).unwrap();
+        let loaded = load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (3, 2));
+        assert_eq!(loaded.buffer, framebuffer.buffer);
+    }
+}
@@ -0,0 +1,28 @@
+pub mod framebuffer;
+pub mod triangle;
+pub mod vertex;
+pub mod obj;
+pub mod color;
+pub mod tonemap;
+pub mod postprocess;
+pub mod fragment;
+pub mod shaders;
+pub mod lighting;
+pub mod shadows;
+pub mod sphere;
+pub mod mesh;
+pub mod noise;
+pub mod orbit;
+pub mod gravity;
+pub mod planets;
+pub mod ship;
+pub mod camera;
+pub mod clipping;
+pub mod render;
+pub mod tiles;
+pub mod scene;
+pub mod scene_file;
+pub mod image;
+pub mod clock;
+pub mod headless;
+pub mod cli;
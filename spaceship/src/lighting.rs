@@ -0,0 +1,74 @@
+use nalgebra_glm::{Vec3, dot};
+use crate::color::{Color, LinearColor};
+use crate::framebuffer::BlendMode;
+use crate::shaders::{FragmentInput, FragmentShader};
+
+// The star's light. Positioned in world space; no distance falloff, so
+// every orbit receives the same irradiance.
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub radius: f32,    // Size of the star, for soft shadow edges (0 = hard)
+    pub color: LinearColor,
+    pub intensity: f32,
+    pub ambient: f32,   // Share of the albedo still visible on the night side
+    pub specular: f32,  // Strength of the Blinn-Phong highlight
+    pub shininess: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        PointLight {
+            position: Vec3::new(0.0, 0.0, 0.0),
+            radius: 0.0,
+            color: Color::white().into(),
+            intensity: 1.0,
+            ambient: 0.04,
+            specular: 0.25,
+            shininess: 32.0,
+        }
+    }
+}
+
+// Uses the color of the wrapped shader as albedo and lights it with the
+// point light in `Uniforms`
+pub struct Lit<S>(pub S);
+
+impl<S: FragmentShader> FragmentShader for Lit<S> {
+    fn shade(&self, input: &FragmentInput) -> LinearColor {
+        illuminate(self.0.shade(input), input)
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.0.blend_mode()
+    }
+}
+
+// Lambert diffuse + Blinn-Phong specular for one fragment, attenuated by
+// the bodies between the fragment and the star
+pub fn illuminate(albedo: LinearColor, input: &FragmentInput) -> LinearColor {
+    let light = &input.uniforms.light;
+
+    let to_view = (input.uniforms.camera_position - input.world_position).normalize();
+    let to_light = (light.position - input.world_position).normalize();
+
+    // Two-sided: thin surfaces such as rings are lit on the side being seen
+    let normal = if dot(&input.normal, &to_view) < 0.0 { -input.normal } else { input.normal };
+
+    let mut diffuse = dot(&normal, &to_light).max(0.0);
+    let mut specular = if diffuse > 0.0 {
+        let half = (to_light + to_view).normalize();
+        dot(&normal, &half).max(0.0).powf(light.shininess) * light.specular
+    } else {
+        0.0
+    };
+
+    if diffuse > 0.0 {
+        let visibility = input.uniforms.occluders.visibility(input.world_position, light.position, light.radius);
+        diffuse *= visibility;
+        specular *= visibility;
+    }
+
+    let light_color = light.color * light.intensity;
+    albedo * light_color * diffuse + albedo * light.ambient + light_color * specular
+}
@@ -1,111 +1,84 @@
-use nalgebra_glm::{Vec3, Mat4};
-use minifb::{Key, Window, WindowOptions};
-use std::time::Duration;
-use std::f32::consts::PI;
-
-mod framebuffer;
-mod triangle;
-mod vertex;
-mod obj;
-mod color;
-mod fragment;
-mod shaders;
-mod sphere;
-mod planets;
-
-use framebuffer::Framebuffer;
-use vertex::Vertex;
-use obj::Obj;
-use triangle::{triangle, triangle_with_shader};
-use shaders::{vertex_shader, Uniforms};
-use planets::{Planet, PlanetType, Moon, Ring};
-
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
-
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
-
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
-
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use std::time::{Duration, Instant};
+
+use spaceship::framebuffer::{Framebuffer, SAMPLE_COUNTS};
+use spaceship::gravity::{Gravity, Integrator};
+use spaceship::planets::PlanetType;
+use spaceship::camera::Camera;
+use spaceship::cli::{parse_args, Mode, Options, USAGE};
+use spaceship::render::{create_frame_uniforms, Renderer, RasterMode};
+use spaceship::scene::{Scene, default_camera};
+use spaceship::scene_file;
+use spaceship::clock::SimulationClock;
+use spaceship::headless::render_to_files;
+use spaceship::noise::set_shared_seed;
+use spaceship::postprocess::{default_post_process, PostProcess};
+use spaceship::tonemap::ToneMapping;
 
-    let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
-    );
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    transform_matrix * rotation_matrix
-}
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
-    render_with_shader(framebuffer, uniforms, vertex_array, None, 0.0)
-}
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-fn render_with_shader(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: Option<PlanetType>, time: f32) {
-    // Vertex Shader Stage
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
+    // Must happen before any shader samples the noise
+    set_shared_seed(options.seed);
 
-    // Primitive Assembly Stage - manually iterate through faces
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    let mut scene = match load_scene(&options) {
+        Ok(scene) => scene,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
         }
+    };
+    if let Some(integrator) = options.gravity {
+        scene.enable_physics(Gravity { integrator, ..Gravity::default() });
     }
 
-    // Rasterization Stage - draw all triangles
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle_with_shader(&tri[0], &tri[1], &tri[2], shader_type.clone(), time));
+    match options.mode {
+        Mode::Headless => match render_to_files(&mut scene, &default_camera(), &options.headless()) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("Wrote {}", path.display());
+                }
+                if let Some(physics) = scene.physics() {
+                    println!("N-body ({}): {}", physics.gravity.integrator.name(), physics.diagnostics());
+                }
+            }
+            Err(error) => {
+                eprintln!("Failed to write frames: {}", error);
+                std::process::exit(1);
+            }
+        },
+        Mode::Interactive => run_interactive(scene, &options),
     }
+}
 
-    // Fragment Processing Stage
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let color = fragment.color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
+fn load_scene(options: &Options) -> Result<Scene, String> {
+    match &options.scene {
+        Some(path) => scene_file::load_scene(path),
+        None => Ok(Scene::solar_system()),
     }
 }
 
-fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+// Longest real-time gap the simulation catches up on in one frame
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+fn run_interactive(mut scene: Scene, options: &Options) {
+    let (window_width, window_height) = (options.width, options.height);
+    let (framebuffer_width, framebuffer_height) = options.framebuffer_size();
+    let frame_time = options.frame_time();
+    let frame_budget = Duration::from_secs_f32(frame_time);
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
     let mut window = Window::new(
@@ -121,94 +94,13 @@ fn main() {
 
     // Set background color to deep space
     framebuffer.set_background_color(0x000011);
+    framebuffer.set_samples(options.samples);
 
-    // Create planetary system for maximum points
-    let mut planets = Vec::new();
-    let mut moons = Vec::new();
-    let mut rings = Vec::new();
-
-    // Sun (Star) - Center of the system
-    planets.push(Planet::new(
-        PlanetType::Star,
-        Vec3::new(400.0, 300.0, 0.0), // Center of screen
-        60.0, // Large size
-        0.5,  // Slow rotation
-        0.0,  // No orbital motion (it's the center)
-        0.0,
-    ));
-
-    // Rocky Planet (Earth-like) with moon
-    planets.push(Planet::new(
-        PlanetType::RockyPlanet,
-        Vec3::new(400.0, 300.0, 0.0),
-        25.0,
-        2.0,  // Rotation
-        1.0,  // Orbital speed
-        120.0, // Orbital radius
-    ));
-
-    // Moon for the rocky planet
-    moons.push(Moon::new(
-        Vec3::new(0.0, 0.0, 0.0), // Will be updated
-        40.0, // Orbital radius around planet
-        3.0,  // Fast orbital speed
-        8.0,  // Small size
-    ));
-
-    // Gas Giant with rings
-    planets.push(Planet::new(
-        PlanetType::GasGiant,
-        Vec3::new(400.0, 300.0, 0.0),
-        45.0, // Planet radius = 45 units
-        1.5,
-        0.7,
-        200.0,
-    ));
-
-    // Rings for gas giant - proper spacing from planet surface
-    // Gas Giant radius = 45, so rings start at ~70 (25 units gap)
-    rings.push(Ring::new(90.0, 140.0, 128)); // Main ring system 
-    rings.push(Ring::new(150.0, 180.0, 96)); // Outer ring (10 unit gap)
-    rings.push(Ring::new(70.0, 85.0, 96));   // Inner ring (closest to planet)
-
-    // Extra planets for bonus points
-    // Ice Planet
-    planets.push(Planet::new(
-        PlanetType::IcePlanet,
-        Vec3::new(400.0, 300.0, 0.0),
-        20.0,
-        1.0,
-        0.5,
-        280.0,
-    ));
-
-    // Volcanic Planet
-    planets.push(Planet::new(
-        PlanetType::VolcanicPlanet,
-        Vec3::new(400.0, 300.0, 0.0),
-        18.0,
-        3.0,
-        1.5,
-        80.0, // Close to the sun
-    ));
-
-    // Ringed Planet (Saturn-like)
-    planets.push(Planet::new(
-        PlanetType::RingedPlanet,
-        Vec3::new(400.0, 300.0, 0.0),
-        35.0, // Planet radius = 35 units  
-        1.2,
-        0.4,
-        320.0,
-    ));
-
-    // Rings for ringed planet - Saturn-like with proper spacing  
-    // Ringed Planet radius = 35, so rings start at ~65 (30 units gap)
-    rings.push(Ring::new(80.0, 120.0, 128));  // Main A ring
-    rings.push(Ring::new(130.0, 160.0, 96));  // B ring (10 unit gap)
-    rings.push(Ring::new(65.0, 75.0, 64));    // Inner C ring (closest to planet)
-
-    let start_time = std::time::Instant::now();
+    let mut clock = SimulationClock::new(0.0, options.time_scale);
+    clock.seek(options.start_time, |dt| scene.update(dt));
+    let raster_mode = RasterMode::tiled();
+
+    let mut last_frame = Instant::now();
     let mut current_planet = 0; // For cycling through planets
     
     println!("🌟 SOLAR SYSTEM RENDERER 🌟");
@@ -224,195 +116,208 @@ fn main() {
     println!("✓ Ring systems - around gas giants");
     println!("=====================================");
     println!("Controls:");
-    println!("• Arrow Keys: Navigate camera");
+    println!("• Arrow Keys: Orbit camera");
     println!("• S/A: Zoom in/out");
     println!("• 1-6: Focus on different planets");
-    println!("• SPACE: Toggle auto-rotation");
+    println!("• SPACE: Pause/resume the simulation");
+    println!("• . (period): Single step while paused");
+    println!("• [ / ]: Slow down/speed up time (0x-1000x)");
+    println!("• R: Reverse time");
+    println!("• T: Cycle tone mapping (Reinhard/ACES/Filmic)");
+    println!("• =/-: Exposure up/down");
+    println!("• B: Toggle bloom");
+    println!("• F: Toggle FXAA");
+    println!("• M: Cycle MSAA (1x/2x/4x/8x)");
+    println!("• G: Toggle N-body gravity (kinematic orbits when off)");
+    println!("• I: Print N-body energy/momentum drift");
     println!("• ESC: Exit");
     println!("=====================================");
 
-    let mut camera_position = Vec3::new(400.0, 300.0, 0.0);
-    let mut camera_scale = 1.0;
-    let mut auto_rotate = true;
+    let mut camera = default_camera();
+    let mut tone_mapping = ToneMapping::default();
+    let mut post_process = default_post_process();
+    let integrator = options.gravity.unwrap_or_default();
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
-        let elapsed = start_time.elapsed().as_secs_f32();
+        // Real time since the last frame; after a stall (window dragged,
+        // debugger) the simulation skips ahead at most MAX_CATCH_UP
+        let frame_start = Instant::now();
+        let real_elapsed = frame_start.duration_since(last_frame).min(MAX_CATCH_UP).as_secs_f32();
+        last_frame = frame_start;
 
         // Handle input
-        handle_input(&window, &mut camera_position, &mut camera_scale, &mut current_planet, &mut auto_rotate, &planets);
+        handle_input(&window, &mut camera, &mut current_planet, &scene);
+        handle_clock_input(&window, &mut clock, &mut scene);
+        handle_tone_mapping_input(&window, &mut tone_mapping);
+        handle_post_process_input(&window, &mut post_process, &mut framebuffer);
+        handle_gravity_input(&window, &mut scene, integrator);
 
-        // Update planetary positions
-        let delta_time = 0.016; // Assuming ~60 FPS
-        for planet in &mut planets {
-            planet.update(delta_time);
-        }
-
-        // Update moon positions
-        if planets.len() > 1 { // Make sure rocky planet exists
-            let rocky_planet_pos = planets[1].get_current_position();
-            for moon in &mut moons {
-                moon.update(delta_time, rocky_planet_pos);
-            }
-        }
+        // Fixed-step updates for the real time that passed; the shaders see
+        // the same simulated time as the bodies
+        clock.tick(real_elapsed, |dt| scene.update(dt));
+        let elapsed = clock.time();
 
         framebuffer.clear();
 
-        // Render all planets
-        for (i, planet) in planets.iter().enumerate() {
-            let translation = planet.get_current_position();
-            let rotation = Vec3::new(0.0, planet.current_rotation, 0.0);
-            let scale = planet.scale * camera_scale;
-
-            let model_matrix = create_model_matrix(
-                translation + camera_position - Vec3::new(400.0, 300.0, 0.0),
-                scale,
-                rotation
-            );
-            let uniforms = Uniforms { model_matrix };
-
-            render_with_shader(
-                &mut framebuffer,
-                &uniforms,
-                planet.sphere.get_vertex_array(),
-                Some(planet.planet_type.clone()),
-                elapsed
-            );
-
-            // Render rings if this is a gas giant or ringed planet
-            if matches!(planet.planet_type, PlanetType::GasGiant) && i == 2 && rings.len() >= 3 {
-                let ring_translation = translation + camera_position - Vec3::new(400.0, 300.0, 0.0);
-                // Render multiple rings for gas giant
-                render_ring(&mut framebuffer, &rings[0], ring_translation, camera_scale * planet.scale, elapsed);
-                render_ring(&mut framebuffer, &rings[1], ring_translation, camera_scale * planet.scale, elapsed);
-                render_ring(&mut framebuffer, &rings[2], ring_translation, camera_scale * planet.scale, elapsed);
-            }
-            if matches!(planet.planet_type, PlanetType::RingedPlanet) && rings.len() >= 6 {
-                let ring_translation = translation + camera_position - Vec3::new(400.0, 300.0, 0.0);
-                // Render multiple rings for ringed planet (Saturn-like)
-                render_ring(&mut framebuffer, &rings[3], ring_translation, camera_scale * planet.scale, elapsed);
-                render_ring(&mut framebuffer, &rings[4], ring_translation, camera_scale * planet.scale, elapsed);
-                render_ring(&mut framebuffer, &rings[5], ring_translation, camera_scale * planet.scale, elapsed);
-            }
-        }
-
-        // Render moons
-        if !moons.is_empty() && planets.len() > 1 {
-            let moon_pos = moons[0].get_current_position();
-            let model_matrix = create_model_matrix(
-                moon_pos + camera_position - Vec3::new(400.0, 300.0, 0.0),
-                moons[0].scale * camera_scale,
-                Vec3::new(0.0, 0.0, 0.0)
-            );
-            let uniforms = Uniforms { model_matrix };
-
-            // Use a simple gray color for moon
-            framebuffer.set_current_color(0xAAAAA0);
-            render(&mut framebuffer, &uniforms, moons[0].sphere.get_vertex_array());
-        }
+        // Camera matrices are shared by every body this frame
+        let frame_uniforms = create_frame_uniforms(&camera, framebuffer_width, framebuffer_height);
+        let mut renderer = Renderer::new(raster_mode);
+        scene.render(&mut framebuffer, &mut renderer, &frame_uniforms, elapsed);
+        post_process.apply(&mut framebuffer, elapsed);
+        framebuffer.resolve(&tone_mapping);
 
         window
             .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
             .unwrap();
 
-        std::thread::sleep(frame_delay);
+        // Sleep off what is left of the frame budget
+        if let Some(remaining) = frame_budget.checked_sub(frame_start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
     }
 }
 
-fn render_ring(framebuffer: &mut Framebuffer, ring: &Ring, center: Vec3, scale: f32, time: f32) {
-    // Set ring color - make it more visible
-    framebuffer.set_current_color(0xDDDDEE);
-    
-    // Create transformation matrix for the ring
-    let ring_scale = scale * 0.012; // Adjust scale to be more visible
-    let rotation = Vec3::new(75.0_f32.to_radians(), time * 0.2, 0.0); // Slight tilt and slow rotation
-    let model_matrix = create_model_matrix(center, ring_scale, rotation);
-    let uniforms = Uniforms { model_matrix };
-    
-    // Render the ring
-    render(framebuffer, &uniforms, &ring.vertices);
-}fn handle_input(
+fn handle_input(
     window: &Window, 
-    camera_position: &mut Vec3, 
-    camera_scale: &mut f32, 
+    camera: &mut Camera, 
     current_planet: &mut usize,
-    auto_rotate: &mut bool,
-    planets: &[Planet]
+    scene: &Scene
 ) {
-    // Camera movement
+    // Camera orbit around the current focus
     if window.is_key_down(Key::Right) {
-        camera_position.x -= 10.0;
+        camera.orbit(-0.03, 0.0);
     }
     if window.is_key_down(Key::Left) {
-        camera_position.x += 10.0;
+        camera.orbit(0.03, 0.0);
     }
     if window.is_key_down(Key::Up) {
-        camera_position.y += 10.0;
+        camera.orbit(0.0, 0.03);
     }
     if window.is_key_down(Key::Down) {
-        camera_position.y -= 10.0;
+        camera.orbit(0.0, -0.03);
     }
     
     // Zoom
     if window.is_key_down(Key::S) {
-        *camera_scale += 0.05;
+        camera.zoom(20.0, 50.0);
     }
     if window.is_key_down(Key::A) {
-        *camera_scale -= 0.05;
-        if *camera_scale < 0.1 {
-            *camera_scale = 0.1;
-        }
+        camera.zoom(-20.0, 50.0);
     }
     
     // Planet selection (1-6 keys)
-    if window.is_key_down(Key::Key1) {
-        *current_planet = 0;
-        focus_on_planet(camera_position, planets, 0);
+    let keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6];
+    for (index, key) in keys.into_iter().enumerate() {
+        if window.is_key_down(key) && focus_on_planet(camera, scene, index) {
+            *current_planet = index;
+        }
     }
-    if window.is_key_down(Key::Key2) && planets.len() > 1 {
-        *current_planet = 1;
-        focus_on_planet(camera_position, planets, 1);
+}
+
+fn handle_clock_input(window: &Window, clock: &mut SimulationClock, scene: &mut Scene) {
+    // Each key is checked on its own, so presses landing in the same frame all apply
+    let pressed = |key| window.is_key_pressed(key, KeyRepeat::No);
+    let mut changed = false;
+
+    if pressed(Key::Space) {
+        clock.toggle_pause();
+        changed = true;
     }
-    if window.is_key_down(Key::Key3) && planets.len() > 2 {
-        *current_planet = 2;
-        focus_on_planet(camera_position, planets, 2);
+    if pressed(Key::Period) && clock.is_paused() {
+        clock.single_step(|dt| scene.update(dt));
+        changed = true;
     }
-    if window.is_key_down(Key::Key4) && planets.len() > 3 {
-        *current_planet = 3;
-        focus_on_planet(camera_position, planets, 3);
+    if pressed(Key::RightBracket) {
+        clock.faster();
+        changed = true;
     }
-    if window.is_key_down(Key::Key5) && planets.len() > 4 {
-        *current_planet = 4;
-        focus_on_planet(camera_position, planets, 4);
+    if pressed(Key::LeftBracket) {
+        clock.slower();
+        changed = true;
     }
-    if window.is_key_down(Key::Key6) && planets.len() > 5 {
-        *current_planet = 5;
-        focus_on_planet(camera_position, planets, 5);
+    if pressed(Key::R) {
+        clock.toggle_reverse();
+        changed = true;
     }
-    
-    // Toggle auto-rotation
-    if window.is_key_down(Key::Space) {
-        *auto_rotate = !*auto_rotate;
-        std::thread::sleep(Duration::from_millis(200)); // Prevent rapid toggling
+
+    if changed {
+        println!("Time: {}", clock);
     }
 }
 
-fn focus_on_planet(camera_position: &mut Vec3, planets: &[Planet], index: usize) {
-    if index < planets.len() {
-        let planet_pos = planets[index].get_current_position();
-        *camera_position = Vec3::new(400.0, 300.0, 0.0) - planet_pos;
-        println!("Focusing on planet {}: {:?}", index + 1, 
-            match planets[index].planet_type {
-                PlanetType::Star => "Star (Sun)",
-                PlanetType::RockyPlanet => "Rocky Planet (Earth-like)",
-                PlanetType::GasGiant => "Gas Giant (Jupiter-like)",
-                PlanetType::IcePlanet => "Ice Planet",
-                PlanetType::VolcanicPlanet => "Volcanic Planet",
-                PlanetType::RingedPlanet => "Ringed Planet (Saturn-like)",
-            }
-        );
+fn handle_tone_mapping_input(window: &Window, tone_mapping: &mut ToneMapping) {
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+        tone_mapping.operator = tone_mapping.operator.next();
+        println!("Tone mapping: {}", tone_mapping.operator.name());
+    }
+
+    // Exposure in small multiplicative steps while the key is held
+    if window.is_key_down(Key::Equal) {
+        tone_mapping.exposure = (tone_mapping.exposure * 1.03).min(16.0);
+    }
+    if window.is_key_down(Key::Minus) {
+        tone_mapping.exposure = (tone_mapping.exposure / 1.03).max(0.05);
+    }
+}
+
+fn handle_post_process_input(window: &Window, post_process: &mut PostProcess, framebuffer: &mut Framebuffer) {
+    for (key, pass) in [(Key::B, "bloom"), (Key::F, "fxaa")] {
+        if window.is_key_pressed(key, KeyRepeat::No) {
+            let enabled = !post_process.is_enabled(pass);
+            post_process.set_enabled(pass, enabled);
+            println!("{}: {}", pass.to_uppercase(), if enabled { "on" } else { "off" });
+        }
+    }
+
+    // Cycle through the MSAA sample counts
+    if window.is_key_pressed(Key::M, KeyRepeat::No) {
+        let index = SAMPLE_COUNTS.iter().position(|&n| n == framebuffer.samples()).unwrap_or(0);
+        let samples = SAMPLE_COUNTS[(index + 1) % SAMPLE_COUNTS.len()];
+        framebuffer.set_samples(samples);
+        println!("MSAA: {}x", samples);
+    }
+}
+
+fn handle_gravity_input(window: &Window, scene: &mut Scene, integrator: Integrator) {
+    if window.is_key_pressed(Key::G, KeyRepeat::No) {
+        if scene.physics().is_some() {
+            scene.disable_physics();
+            println!("N-body gravity: off");
+        } else {
+            scene.enable_physics(Gravity { integrator, ..Gravity::default() });
+            println!("N-body gravity: on ({})", integrator.name());
+        }
     }
+
+    if window.is_key_pressed(Key::I, KeyRepeat::No) {
+        match scene.physics() {
+            Some(physics) => println!("N-body after {:.1}s: {}", physics.time(), physics.diagnostics()),
+            None => println!("N-body gravity is off"),
+        }
+    }
+}
+
+// Focuses the camera on the scene's `index`-th planet, if there is one
+fn focus_on_planet(camera: &mut Camera, scene: &Scene, index: usize) -> bool {
+    let Some((id, planet)) = scene.planets().nth(index) else {
+        return false;
+    };
+
+    let node = scene.node(id);
+    camera.focus(node.world_position());
+    println!("Focusing on planet {}: {} ({})", index + 1, node.name,
+        match planet.planet_type {
+            PlanetType::Star => "Star",
+            PlanetType::RockyPlanet => "Rocky Planet",
+            PlanetType::GasGiant => "Gas Giant",
+            PlanetType::IcePlanet => "Ice Planet",
+            PlanetType::VolcanicPlanet => "Volcanic Planet",
+            PlanetType::RingedPlanet => "Ringed Planet",
+        }
+    );
+    true
 }
@@ -0,0 +1,22 @@
+use crate::vertex::Vertex;
+
+// Indexed triangle list: every three indices form a triangle and
+// vertices shared between triangles are stored (and transformed) once
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        Mesh { vertices, indices }
+    }
+
+    // Expand the index buffer into a flat vertex list (three per triangle)
+    pub fn to_vertex_array(&self) -> Vec<Vertex> {
+        self.indices
+            .iter()
+            .map(|&index| self.vertices[index as usize].clone())
+            .collect()
+    }
+}
@@ -0,0 +1,349 @@
+use nalgebra_glm::Vec3;
+use std::sync::OnceLock;
+
+// Seed of the noise shared by the built-in planet shaders
+pub const DEFAULT_SEED: u32 = 1337;
+
+// Octave settings for the fractal variants
+#[derive(Clone, Copy, Debug)]
+pub struct Fractal {
+    pub octaves: u32,
+    pub lacunarity: f32, // Frequency multiplier per octave
+    pub gain: f32,       // Amplitude multiplier per octave
+}
+
+impl Fractal {
+    pub fn new(octaves: u32, lacunarity: f32, gain: f32) -> Self {
+        Fractal { octaves, lacunarity, gain }
+    }
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Fractal::new(4, 2.0, 0.5)
+    }
+}
+
+// Seeded 3D noise generators. Every function takes a point in 3D so planet
+// shaders can sample the sphere surface directly, without UV seams or
+// pinching at the poles.
+pub struct Noise {
+    perm: [u8; 512],
+}
+
+// Gradient directions for simplex noise (edges of a cube)
+const GRAD3: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher–Yates shuffle driven by xorshift32
+        let mut state = seed.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+        if state == 0 {
+            state = 1;
+        }
+        for i in (1..256).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let j = (state % (i as u32 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        let mut perm = [0u8; 512];
+        for i in 0..512 {
+            perm[i] = table[i & 255];
+        }
+
+        Noise { perm }
+    }
+
+    fn hash(&self, x: i32, y: i32, z: i32) -> usize {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        let z = (z & 255) as usize;
+        self.perm[self.perm[self.perm[x] as usize + y] as usize + z] as usize
+    }
+
+    // Classic (improved) Perlin gradient noise in [-1, 1]
+    pub fn perlin(&self, p: Vec3) -> f32 {
+        let (xi, yi, zi) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+        let (x, y, z) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            let h = self.hash(xi + dx, yi + dy, zi + dz);
+            gradient(h, x - dx as f32, y - dy as f32, z - dz as f32)
+        };
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+        .clamp(-1.0, 1.0)
+    }
+
+    // Simplex noise in [-1, 1]; cheaper than Perlin and without axis-aligned artifacts
+    pub fn simplex(&self, p: Vec3) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        // Skew into the simplex grid to find the containing cell
+        let s = (p.x + p.y + p.z) * F3;
+        let (i, j, k) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+        let t = (i + j + k) * G3;
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+
+        // Which of the six tetrahedra of the cell we are in
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1, 0, 0, 1, 1, 0)
+            } else if x0 >= z0 {
+                (1, 0, 0, 1, 0, 1)
+            } else {
+                (0, 0, 1, 1, 0, 1)
+            }
+        } else if y0 < z0 {
+            (0, 0, 1, 0, 1, 1)
+        } else if x0 < z0 {
+            (0, 1, 0, 0, 1, 1)
+        } else {
+            (0, 1, 0, 1, 1, 0)
+        };
+
+        let corners = [
+            (0, 0, 0, x0, y0, z0),
+            (i1, j1, k1, x0 - i1 as f32 + G3, y0 - j1 as f32 + G3, z0 - k1 as f32 + G3),
+            (i2, j2, k2, x0 - i2 as f32 + 2.0 * G3, y0 - j2 as f32 + 2.0 * G3, z0 - k2 as f32 + 2.0 * G3),
+            (1, 1, 1, x0 - 1.0 + 3.0 * G3, y0 - 1.0 + 3.0 * G3, z0 - 1.0 + 3.0 * G3),
+        ];
+
+        let (i, j, k) = (i as i32, j as i32, k as i32);
+        let mut total = 0.0;
+        for (di, dj, dk, x, y, z) in corners {
+            let falloff = 0.6 - x * x - y * y - z * z;
+            if falloff > 0.0 {
+                let g = GRAD3[self.hash(i + di, j + dj, k + dk) % 12];
+                let falloff = falloff * falloff;
+                total += falloff * falloff * (g[0] * x + g[1] * y + g[2] * z);
+            }
+        }
+
+        (32.0 * total).clamp(-1.0, 1.0)
+    }
+
+    // Value noise in [-1, 1]: smoothly interpolated random lattice values
+    pub fn value(&self, p: Vec3) -> f32 {
+        let (xi, yi, zi) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+        let (u, v, w) = (fade(p.x - p.x.floor()), fade(p.y - p.y.floor()), fade(p.z - p.z.floor()));
+
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            self.hash(xi + dx, yi + dy, zi + dz) as f32 / 255.0 * 2.0 - 1.0
+        };
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    // Worley (cellular) noise: distances to the nearest and second nearest
+    // feature points, one feature point per unit cell
+    pub fn worley(&self, p: Vec3) -> (f32, f32) {
+        let (xi, yi, zi) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+        let mut f1 = f32::MAX;
+        let mut f2 = f32::MAX;
+
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (cx, cy, cz) = (xi + dx, yi + dy, zi + dz);
+                    let h = self.hash(cx, cy, cz);
+                    let feature = Vec3::new(
+                        cx as f32 + self.perm[h] as f32 / 255.0,
+                        cy as f32 + self.perm[h + 1] as f32 / 255.0,
+                        cz as f32 + self.perm[h + 2] as f32 / 255.0,
+                    );
+
+                    let distance = (feature - p).magnitude();
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+
+        (f1, f2)
+    }
+
+    // Fractional Brownian motion: sum of Perlin octaves, roughly in [-1, 1]
+    pub fn fbm(&self, p: Vec3, fractal: Fractal) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut normalization = 0.0;
+
+        for octave in 0..fractal.octaves {
+            // Offset each octave so lattice artifacts don't line up
+            let offset = octave as f32 * 17.31;
+            total += amplitude * self.perlin(p * frequency + Vec3::new(offset, offset, offset));
+            normalization += amplitude;
+            amplitude *= fractal.gain;
+            frequency *= fractal.lacunarity;
+        }
+
+        if normalization > 0.0 { total / normalization } else { 0.0 }
+    }
+
+    // Ridged multifractal in [0, 1]: sharp crests where the noise crosses zero
+    pub fn ridged(&self, p: Vec3, fractal: Fractal) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut normalization = 0.0;
+        let mut weight = 1.0;
+
+        for octave in 0..fractal.octaves {
+            let offset = octave as f32 * 17.31;
+            let ridge = 1.0 - self.simplex(p * frequency + Vec3::new(offset, offset, offset)).abs();
+            let ridge = ridge * ridge * weight;
+
+            // Crests of lower octaves sharpen the higher ones
+            weight = ridge.clamp(0.0, 1.0);
+            total += amplitude * ridge;
+            normalization += amplitude;
+            amplitude *= fractal.gain;
+            frequency *= fractal.lacunarity;
+        }
+
+        if normalization > 0.0 { (total / normalization).clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    // fBm sampled at a position displaced by another fBm field
+    pub fn domain_warp(&self, p: Vec3, strength: f32, fractal: Fractal) -> f32 {
+        let warp = Vec3::new(
+            self.fbm(p, fractal),
+            self.fbm(p + Vec3::new(5.2, 1.3, 2.8), fractal),
+            self.fbm(p + Vec3::new(1.7, 9.2, 4.1), fractal),
+        );
+
+        self.fbm(p + warp * strength, fractal)
+    }
+}
+
+static SHARED: OnceLock<Noise> = OnceLock::new();
+
+// Noise instance shared by the built-in shaders
+pub fn shared() -> &'static Noise {
+    SHARED.get_or_init(|| Noise::new(DEFAULT_SEED))
+}
+
+// Picks the seed of the shared noise. Only works before the first shader
+// samples it; returns false if it is already in use.
+pub fn set_shared_seed(seed: u32) -> bool {
+    SHARED.set(Noise::new(seed)).is_ok()
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_points() -> impl Iterator<Item = Vec3> {
+        (0..500).map(|i| {
+            let t = i as f32 * 0.137;
+            Vec3::new(t.sin() * 7.3 + t, (t * 1.3).cos() * 5.1, t * 0.71 - 20.0)
+        })
+    }
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let a = Noise::new(7);
+        let b = Noise::new(7);
+        let c = Noise::new(8);
+
+        let p = Vec3::new(1.3, -2.7, 0.45);
+        assert_eq!(a.perlin(p), b.perlin(p));
+        assert_eq!(a.simplex(p), b.simplex(p));
+        assert_eq!(a.worley(p), b.worley(p));
+        assert!(sample_points().any(|p| a.perlin(p) != c.perlin(p)));
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        let noise = Noise::new(DEFAULT_SEED);
+        let fractal = Fractal::default();
+
+        for p in sample_points() {
+            for n in [noise.perlin(p), noise.simplex(p), noise.value(p), noise.fbm(p, fractal)] {
+                assert!((-1.0..=1.0).contains(&n));
+            }
+            assert!((0.0..=1.0).contains(&noise.ridged(p, fractal)));
+
+            let (f1, f2) = noise.worley(p);
+            assert!(f1 <= f2);
+            assert!(f1 < 3.0_f32.sqrt());
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let noise = Noise::new(DEFAULT_SEED);
+        let step = Vec3::new(1e-3, 1e-3, 1e-3);
+
+        for p in sample_points() {
+            assert!((noise.perlin(p) - noise.perlin(p + step)).abs() < 0.05);
+            assert!((noise.simplex(p) - noise.simplex(p + step)).abs() < 0.05);
+            assert!((noise.value(p) - noise.value(p + step)).abs() < 0.05);
+        }
+    }
+}
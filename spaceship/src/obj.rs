@@ -1,12 +1,12 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::mesh::Mesh;
 
 pub struct Obj {
-    meshes: Vec<Mesh>,
+    meshes: Vec<ObjMesh>,
 }
 
-struct Mesh {
+struct ObjMesh {
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
@@ -23,7 +23,7 @@ impl Obj {
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
-            Mesh {
+            ObjMesh {
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], -v[1], -v[2]))
                     .collect(),
@@ -40,23 +40,56 @@ impl Obj {
         Ok(Obj { meshes })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    // All the model's meshes merged into a single indexed mesh
+    pub fn get_mesh(&self) -> Mesh {
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
+            let base = vertices.len() as u32;
+
+            for (i, &position) in mesh.vertices.iter().enumerate() {
+                let normal = mesh.normals.get(i)
                     .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
+                let tex_coords = mesh.texcoords.get(i)
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
+
+            indices.extend(mesh.indices.iter().map(|&index| base + index));
+        }
+
+        Mesh::new(vertices, indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(offset: f32) -> ObjMesh {
+        ObjMesh {
+            vertices: [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+                .map(|(x, y)| Vec3::new(x + offset, y, 0.0))
+                .to_vec(),
+            normals: Vec::new(),
+            texcoords: Vec::new(),
+            indices: vec![0, 1, 2, 0, 2, 3],
         }
+    }
+
+    #[test]
+    fn merged_meshes_keep_their_shared_vertices() {
+        let obj = Obj { meshes: vec![quad(0.0), quad(5.0)] };
+        let mesh = obj.get_mesh();
 
-        vertices
+        // Four vertices per quad, not six, and the second quad's indices
+        // point past the first quad's vertices
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]);
+        assert_eq!(mesh.vertices[mesh.indices[6] as usize].position, Vec3::new(5.0, 0.0, 0.0));
     }
 }
@@ -0,0 +1,169 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Keplerian orbital elements, relative to the parent body. The reference
+// plane is XZ (y up) and the reference direction +X, so an orbit with only
+// a semi-major axis and a mean motion is the old circle in the XZ plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,     // 0 is a circle, up to (but not including) 1
+    pub inclination: f32,      // Tilt of the orbital plane, radians
+    pub ascending_node: f32,   // Longitude of the ascending node, radians
+    pub periapsis: f32,        // Argument of periapsis, radians
+    pub mean_anomaly: f32,     // Mean anomaly at epoch (time 0), radians
+    pub mean_motion: f32,      // Average angular speed, radians per second
+}
+
+impl Orbit {
+    pub fn circular(radius: f32, speed: f32) -> Self {
+        Orbit {
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            periapsis: 0.0,
+            mean_anomaly: 0.0,
+            mean_motion: speed,
+        }
+    }
+
+    // Offset from the parent at `time` seconds after epoch
+    pub fn position(&self, time: f32) -> Vec3 {
+        let e = self.eccentricity;
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly + self.mean_motion * time, e);
+
+        // Position in the orbital plane, periapsis along +X
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+        let x = self.semi_major_axis * (cos_e - e);
+        let z = self.semi_major_axis * (1.0 - e * e).sqrt() * sin_e;
+
+        self.orient(x, z)
+    }
+
+    // Velocity relative to the parent at `time`
+    pub fn velocity(&self, time: f32) -> Vec3 {
+        let e = self.eccentricity;
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly + self.mean_motion * time, e);
+
+        // Derivative of the orbital plane position; E changes fastest at
+        // periapsis
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+        let anomaly_rate = self.mean_motion / (1.0 - e * cos_e);
+        let x = -self.semi_major_axis * sin_e * anomaly_rate;
+        let z = self.semi_major_axis * (1.0 - e * e).sqrt() * cos_e * anomaly_rate;
+
+        self.orient(x, z)
+    }
+
+    // From the orbital plane to the parent's frame: rotate by the argument
+    // of periapsis, tilt about the line of nodes, then turn the line of
+    // nodes to its longitude
+    fn orient(&self, x: f32, z: f32) -> Vec3 {
+        let (x, z) = rotate(x, z, self.periapsis);
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (y, z) = (z * sin_i, z * cos_i);
+        let (x, z) = rotate(x, z, self.ascending_node);
+
+        Vec3::new(x, y, z)
+    }
+}
+
+// Turns (x, z) from +X towards +Z, the direction bodies orbit in
+fn rotate(x: f32, z: f32, angle: f32) -> (f32, f32) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - z * sin, x * sin + z * cos)
+}
+
+// Eccentric anomaly E with E - e·sin(E) = M, by Newton's method
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    // Wrapping to [-π, π] keeps the iteration well conditioned at any time
+    let m = (mean_anomaly + PI).rem_euclid(2.0 * PI) - PI;
+    let e = eccentricity;
+
+    // Starting at π converges for highly eccentric orbits too
+    let mut anomaly = if e < 0.8 { m } else { PI.copysign(m) };
+    for _ in 0..20 {
+        let step = (anomaly - e * anomaly.sin() - m) / (1.0 - e * anomaly.cos());
+        anomaly -= step;
+        if step.abs() < 1e-6 {
+            break;
+        }
+    }
+
+    // Back to the same turn as the input, so positions stay continuous
+    anomaly + (mean_anomaly - m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).norm() < 1e-2, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn circular_orbit_is_the_old_circle() {
+        let orbit = Orbit::circular(120.0, 1.0);
+        for time in [0.0, 0.7, 2.0, 10.0] {
+            assert_near(orbit.position(time), Vec3::new(120.0 * time.cos(), 0.0, 120.0 * time.sin()));
+        }
+    }
+
+    #[test]
+    fn kepler_equation_is_solved_for_eccentric_orbits() {
+        for e in [0.0, 0.3, 0.7, 0.95] {
+            for m in [-3.0, -1.0, 0.0, 0.5, 2.0, 3.1, 9.0, 40.0] {
+                let anomaly = solve_kepler(m, e);
+                assert!((anomaly - e * anomaly.sin() - m).abs() < 1e-4, "e {} M {}", e, m);
+            }
+        }
+    }
+
+    #[test]
+    fn elliptical_orbit_sweeps_equal_areas_in_equal_times() {
+        let orbit = Orbit { eccentricity: 0.6, ..Orbit::circular(100.0, 1.0) };
+        let period = 2.0 * PI;
+
+        // Closest at periapsis, farthest half a period later
+        assert_near(orbit.position(0.0), Vec3::new(40.0, 0.0, 0.0));
+        assert_near(orbit.position(period / 2.0), Vec3::new(-160.0, 0.0, 0.0));
+
+        // Faster near periapsis, yet the swept area per step is constant
+        let dt = 0.01;
+        let swept = |time: f32| orbit.position(time).cross(&orbit.position(time + dt)).norm() / 2.0;
+        let speed = |time: f32| (orbit.position(time + dt) - orbit.position(time)).norm() / dt;
+        assert!(speed(0.0) > 3.0 * speed(period / 2.0));
+        assert!((swept(0.0) / swept(period / 2.0) - 1.0).abs() < 1e-2);
+        assert!((swept(1.3) / swept(4.0) - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn velocity_is_the_derivative_of_position() {
+        let orbit = Orbit {
+            eccentricity: 0.4,
+            inclination: 0.3,
+            ascending_node: 1.1,
+            periapsis: 2.0,
+            mean_anomaly: 0.5,
+            ..Orbit::circular(100.0, 0.8)
+        };
+        let dt = 1e-3;
+        for time in [0.0, 1.0, 3.7] {
+            let difference = (orbit.position(time + dt) - orbit.position(time - dt)) / (2.0 * dt);
+            assert!((orbit.velocity(time) - difference).norm() < 0.05, "t {}", time);
+        }
+    }
+
+    #[test]
+    fn inclined_orbit_rises_above_the_reference_plane() {
+        let inclination = 30f32.to_radians();
+        let orbit = Orbit { inclination, ascending_node: PI / 2.0, ..Orbit::circular(100.0, 1.0) };
+
+        // Crosses the plane at the ascending node and peaks a quarter turn later
+        assert_near(orbit.position(0.0), Vec3::new(0.0, 0.0, 100.0));
+        assert_near(orbit.position(PI / 2.0), Vec3::new(-100.0 * inclination.cos(), 100.0 * inclination.sin(), 0.0));
+        assert_near(orbit.position(PI), Vec3::new(0.0, 0.0, -100.0));
+    }
+}
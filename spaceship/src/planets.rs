@@ -1,10 +1,15 @@
-use nalgebra_glm::{Vec3, dot};
+use nalgebra_glm::Vec3;
 use crate::vertex::Vertex;
-use crate::color::Color;
+use crate::color::{Color, LinearColor};
+use crate::framebuffer::BlendMode;
 use crate::sphere::Sphere;
-use std::time::Instant;
+use crate::mesh::Mesh;
+use crate::shaders::{Blended, FragmentInput, FragmentShader};
+use crate::noise::{self, Fractal};
+use crate::lighting::Lit;
+use crate::orbit::Orbit;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PlanetType {
     Star,           // Sol con efectos de fuego
     RockyPlanet,    // Planeta rocoso tipo Tierra
@@ -14,29 +19,61 @@ pub enum PlanetType {
     RingedPlanet,   // Planeta con anillos prominentes
 }
 
+impl PlanetType {
+    // Built-in 4-layer shader for each kind of body. Planets are lit by the
+    // star; the star itself is emissive.
+    pub fn default_shader(&self) -> Box<dyn FragmentShader> {
+        match self {
+            PlanetType::Star => Box::new(star_shader),
+            PlanetType::RockyPlanet => Box::new(Lit(rocky_planet_shader)),
+            PlanetType::GasGiant => Box::new(Lit(gas_giant_shader)),
+            PlanetType::IcePlanet => Box::new(Lit(ice_planet_shader)),
+            PlanetType::VolcanicPlanet => Box::new(Lit(volcanic_planet_shader)),
+            PlanetType::RingedPlanet => Box::new(Lit(ringed_planet_shader)),
+        }
+    }
+}
+
 pub struct Planet {
     pub sphere: Sphere,
     pub planet_type: PlanetType,
+    pub shader: Box<dyn FragmentShader>,
     pub position: Vec3,
     pub scale: f32,
     pub rotation_speed: f32,
-    pub orbital_speed: f32,
-    pub orbital_radius: f32,
+    pub orbit: Orbit,
+    pub orbit_time: f32, // Seconds since the orbit's epoch
     pub current_rotation: f32,
-    pub current_orbital_angle: f32,
+    pub mass: f32, // Only felt in the N-body mode
 }
 
+// Positions of planets and moons are relative to their parent in the scene
+// graph: the star they orbit, or the planet for a moon.
 pub struct Moon {
     pub sphere: Sphere,
-    pub orbit_center: Vec3,
-    pub orbital_radius: f32,
-    pub orbital_speed: f32,
-    pub current_angle: f32,
+    pub shader: Box<dyn FragmentShader>,
+    pub orbit: Orbit,
+    pub orbit_time: f32,
     pub scale: f32,
+    pub mass: f32,
 }
 
 pub struct Ring {
-    pub vertices: Vec<Vertex>,
+    pub mesh: Mesh,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub style: RingStyle,
+    pub shader: Box<dyn FragmentShader>,
+}
+
+// Default masses are density × radius³. With G = 1 a star this dense gives
+// the inner planets periods of a few seconds, and planets are light enough
+// to only nudge each other.
+pub const STAR_DENSITY: f32 = 8.0;
+pub const PLANET_DENSITY: f32 = 0.05;
+
+fn default_mass(density: f32, radius: f32) -> f32 {
+    density * radius.powi(3)
 }
 
 impl Planet {
@@ -50,359 +87,468 @@ impl Planet {
     ) -> Self {
         let sphere = Sphere::new(1.0, 32, 32); // Base unit sphere
         
-        Planet {
+        let mut planet = Planet {
             sphere,
             planet_type,
+            shader: planet_type.default_shader(),
             position,
             scale,
             rotation_speed,
-            orbital_speed,
-            orbital_radius,
+            orbit: Orbit::circular(orbital_radius, orbital_speed),
+            orbit_time: 0.0,
             current_rotation: 0.0,
-            current_orbital_angle: 0.0,
-        }
+            mass: default_mass(if planet_type == PlanetType::Star { STAR_DENSITY } else { PLANET_DENSITY }, scale),
+        };
+        planet.update(0.0);
+        planet
+    }
+
+    // Replace the circular orbit with arbitrary orbital elements
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = orbit;
+        self.update(0.0);
+        self
+    }
+
+    // Replace the planet type's default shader with a custom one
+    pub fn with_shader(mut self, shader: Box<dyn FragmentShader>) -> Self {
+        self.shader = shader;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
     }
 
     pub fn update(&mut self, delta_time: f32) {
         self.current_rotation += self.rotation_speed * delta_time;
-        self.current_orbital_angle += self.orbital_speed * delta_time;
+        self.orbit_time += delta_time;
         
         // Update position based on orbital motion
-        if self.orbital_radius > 0.0 {
-            self.position.x = self.orbital_radius * self.current_orbital_angle.cos();
-            self.position.z = self.orbital_radius * self.current_orbital_angle.sin();
+        if self.orbit.semi_major_axis > 0.0 {
+            self.position = self.orbit.position(self.orbit_time);
         }
     }
 
+    // Offset from the parent
     pub fn get_current_position(&self) -> Vec3 {
         self.position
     }
 }
 
 impl Moon {
-    pub fn new(orbit_center: Vec3, orbital_radius: f32, orbital_speed: f32, scale: f32) -> Self {
+    pub fn new(orbital_radius: f32, orbital_speed: f32, scale: f32) -> Self {
         let sphere = Sphere::new(1.0, 16, 16); // Smaller detail for moon
         
         Moon {
             sphere,
-            orbit_center,
-            orbital_radius,
-            orbital_speed,
-            current_angle: 0.0,
+            shader: Box::new(Lit(moon_shader)),
+            orbit: Orbit::circular(orbital_radius, orbital_speed),
+            orbit_time: 0.0,
             scale,
+            mass: default_mass(PLANET_DENSITY, scale),
         }
     }
 
-    pub fn update(&mut self, delta_time: f32, planet_position: Vec3) {
-        self.orbit_center = planet_position;
-        self.current_angle += self.orbital_speed * delta_time;
+    // Replace the cratered moon shader with a custom one
+    pub fn with_shader(mut self, shader: Box<dyn FragmentShader>) -> Self {
+        self.shader = shader;
+        self
+    }
+
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = orbit;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.orbit_time += delta_time;
     }
 
+    // Offset from the planet it orbits
     pub fn get_current_position(&self) -> Vec3 {
-        Vec3::new(
-            self.orbit_center.x + self.orbital_radius * self.current_angle.cos(),
-            self.orbit_center.y,
-            self.orbit_center.z + self.orbital_radius * self.current_angle.sin(),
-        )
+        self.orbit.position(self.orbit_time)
     }
 }
 
 impl Ring {
     pub fn new(inner_radius: f32, outer_radius: f32, segments: u32) -> Self {
-        let mut vertices = Vec::new();
-        
+        let mut vertices = Vec::with_capacity(((segments + 1) * 2) as usize);
+        let mut indices = Vec::with_capacity((segments * 6) as usize);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        // One inner/outer vertex pair per segment boundary; the last pair
+        // closes the loop with v = 1 so the texture coordinates don't wrap
+        for i in 0..=segments {
+            let t = i as f32 / segments as f32;
+            let angle = t * 2.0 * std::f32::consts::PI;
+            let (sin, cos) = angle.sin_cos();
+
+            let inner = Vec3::new(inner_radius * cos, 0.0, inner_radius * sin);
+            let outer = Vec3::new(outer_radius * cos, 0.0, outer_radius * sin);
+
+            vertices.push(Vertex::new(inner, normal, nalgebra_glm::Vec2::new(0.0, t)));
+            vertices.push(Vertex::new(outer, normal, nalgebra_glm::Vec2::new(1.0, t)));
+        }
+
         for i in 0..segments {
-            let angle1 = (i as f32 / segments as f32) * 2.0 * std::f32::consts::PI;
-            let angle2 = ((i + 1) as f32 / segments as f32) * 2.0 * std::f32::consts::PI;
-            
-            let cos1 = angle1.cos();
-            let sin1 = angle1.sin();
-            let cos2 = angle2.cos();
-            let sin2 = angle2.sin();
-            
-            // Inner vertices
-            let inner1 = Vec3::new(inner_radius * cos1, 0.0, inner_radius * sin1);
-            let inner2 = Vec3::new(inner_radius * cos2, 0.0, inner_radius * sin2);
-            
-            // Outer vertices
-            let outer1 = Vec3::new(outer_radius * cos1, 0.0, outer_radius * sin1);
-            let outer2 = Vec3::new(outer_radius * cos2, 0.0, outer_radius * sin2);
-            
-            let normal = Vec3::new(0.0, 1.0, 0.0);
-            
+            let inner1 = i * 2;
+            let outer1 = inner1 + 1;
+            let inner2 = inner1 + 2;
+            let outer2 = inner1 + 3;
+
             // First triangle
-            vertices.push(Vertex::new(inner1, normal, nalgebra_glm::Vec2::new(0.0, 0.0)));
-            vertices.push(Vertex::new(outer1, normal, nalgebra_glm::Vec2::new(1.0, 0.0)));
-            vertices.push(Vertex::new(inner2, normal, nalgebra_glm::Vec2::new(0.0, 1.0)));
-            
+            indices.extend_from_slice(&[inner1, outer1, inner2]);
+
             // Second triangle
-            vertices.push(Vertex::new(inner2, normal, nalgebra_glm::Vec2::new(0.0, 1.0)));
-            vertices.push(Vertex::new(outer1, normal, nalgebra_glm::Vec2::new(1.0, 0.0)));
-            vertices.push(Vertex::new(outer2, normal, nalgebra_glm::Vec2::new(1.0, 1.0)));
+            indices.extend_from_slice(&[inner2, outer1, outer2]);
         }
-        
-        Ring { vertices }
+
+        let style = RingStyle::default();
+        Ring {
+            mesh: Mesh::new(vertices, indices),
+            inner_radius,
+            outer_radius,
+            style,
+            shader: ring_style_shader(style),
+        }
+    }
+
+    // Restyles the ring, replacing its shader with a `RingShader` in that style
+    pub fn with_style(mut self, style: RingStyle) -> Self {
+        self.style = style;
+        self.shader = ring_style_shader(style);
+        self
+    }
+
+    // Replace the ring's shader with a custom one; `style` still drives the
+    // shadow the ring casts
+    pub fn with_shader(mut self, shader: Box<dyn FragmentShader>) -> Self {
+        self.shader = shader;
+        self
     }
 }
 
-// Shader functions for different planet types
-pub fn star_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+fn ring_style_shader(style: RingStyle) -> Box<dyn FragmentShader> {
+    Box::new(Blended(Lit(RingShader(style)), BlendMode::Alpha))
+}
+
+// Radiance of the star's surface relative to its base colors
+pub const STAR_EMISSION: f32 = 2.5;
+
+// Shader functions for different planet types. Patterns are sampled on the
+// unit sphere position, so they have no UV seam and don't pinch at the poles.
+pub fn star_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer star shader for maximum points
     
-    // Layer 1: Core temperature gradient
-    let distance_from_center = (uv.x - 0.5).powi(2) + (uv.y - 0.5).powi(2);
-    let core_intensity = 1.0 - (distance_from_center * 4.0).min(1.0);
+    // Layer 1: Core temperature gradient (hotter towards the equator)
+    let core_intensity = (1.0 - p.y.abs()).powf(0.5);
     
-    // Layer 2: Plasma turbulence
-    let turbulence = ((uv.x * 10.0 + time * 2.0).sin() * 
-                     (uv.y * 8.0 + time * 1.5).cos() * 
-                     (time * 3.0).sin()).abs();
+    // Layer 2: Plasma turbulence, slowly boiling
+    let drift = Vec3::new(time * 0.3, time * 0.2, -time * 0.25);
+    let turbulence = noise.domain_warp(p * 3.0 + drift, 0.8, Fractal::new(3, 2.0, 0.5)) * 0.5 + 0.5;
     
     // Layer 3: Solar flares
-    let flare_noise = ((uv.x * 15.0 + time * 4.0).sin() + 
-                      (uv.y * 12.0 + time * 3.0).cos()).abs();
+    let flare_noise = noise.ridged(p * 4.0 - drift * 1.5, Fractal::new(3, 2.2, 0.5));
     
-    // Layer 4: Corona effect
-    let corona = (1.0 - distance_from_center).powf(0.5) * 
-                (time * 2.0 + uv.x * 20.0).sin().abs();
+    // Layer 4: Corona effect (granulation cells)
+    let (f1, f2) = noise.worley(p * 8.0 + drift * 0.5);
+    let corona = ((f2 - f1) * 2.0).min(1.0);
     
     // Combine layers
-    let red = (255.0 * (0.9 + 0.1 * core_intensity + 0.2 * flare_noise)).min(255.0) as u8;
-    let green = (255.0 * (0.6 * core_intensity + 0.3 * turbulence + 0.1 * corona)).min(255.0) as u8;
-    let blue = (100.0 * (0.2 * core_intensity + 0.5 * turbulence)).min(255.0) as u8;
+    let red = 255.0 * (0.9 + 0.1 * core_intensity + 0.2 * flare_noise);
+    let green = 255.0 * (0.4 * core_intensity + 0.3 * turbulence + 0.2 * corona);
+    let blue = 100.0 * (0.2 * core_intensity + 0.5 * turbulence);
     
-    Color::new(red, green, blue)
+    // Emissive: brighter than any lit surface, left to the tone mapper
+    LinearColor::from_srgb(red, green, blue) * STAR_EMISSION
 }
 
-pub fn rocky_planet_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+pub fn rocky_planet_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer rocky planet shader
     
     // Layer 1: Continental masses
-    let continent_noise = ((uv.x * 8.0).sin() * (uv.y * 6.0).cos()).abs();
-    let is_land = continent_noise > 0.3;
+    let continent_noise = noise.fbm(p * 1.8, Fractal::new(5, 2.0, 0.5)) * 0.5 + 0.5;
+    let is_land = continent_noise > 0.55;
     
-    // Layer 2: Ocean depth
-    let ocean_depth = ((uv.x * 15.0).sin() + (uv.y * 12.0).cos()) * 0.5 + 0.5;
+    // Layer 2: Ocean depth (shallower near the coast)
+    let ocean_depth = (1.0 - (0.55 - continent_noise).max(0.0) * 5.0).clamp(0.0, 1.0);
     
-    // Layer 3: Cloud cover
-    let cloud_cover = ((uv.x * 20.0 + time * 0.5).sin() * 
-                      (uv.y * 18.0 + time * 0.7).cos()).abs();
+    // Layer 3: Cloud cover, swirling with time
+    let wind = Vec3::new(time * 0.05, 0.0, time * 0.03);
+    let cloud_cover = noise.domain_warp(p * 2.5 + wind, 1.2, Fractal::new(4, 2.0, 0.5)) * 0.5 + 0.5;
     
-    // Layer 4: Polar ice caps
-    let polar_factor = (uv.y - 0.5).abs() * 2.0;
-    let ice_caps = polar_factor > 0.8;
+    // Layer 4: Polar ice caps with a ragged edge
+    let polar_factor = p.y.abs() + noise.value(p * 6.0) * 0.08;
+    let ice_caps = polar_factor > 0.85;
     
     if ice_caps {
-        Color::new(240, 248, 255) // Ice white
+        LinearColor::from_srgb(240.0, 248.0, 255.0) // Ice white
+    } else if cloud_cover > 0.65 {
+        LinearColor::from_srgb(220.0, 220.0, 220.0) // Clouds
     } else if is_land {
-        if cloud_cover > 0.6 {
-            Color::new(220, 220, 220) // Clouds
-        } else {
-            // Land colors
-            let red = (120.0 + 60.0 * continent_noise) as u8;
-            let green = (80.0 + 40.0 * continent_noise) as u8;
-            let blue = 40;
-            Color::new(red, green, blue)
-        }
+        // Land colors
+        let red = 120.0 + 60.0 * continent_noise;
+        let green = 80.0 + 40.0 * continent_noise;
+        let blue = 40.0;
+        LinearColor::from_srgb(red, green, blue)
     } else {
         // Ocean colors
-        let blue_intensity = (100.0 + 155.0 * ocean_depth) as u8;
-        Color::new(30, 60, blue_intensity)
+        let blue_intensity = 100.0 + 155.0 * ocean_depth;
+        LinearColor::from_srgb(30.0, 60.0, blue_intensity)
     }
 }
 
-pub fn gas_giant_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+pub fn gas_giant_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer gas giant shader
     
-    // Layer 1: Atmospheric bands
-    let band_position = (uv.y * 12.0).sin();
-    let band_intensity = band_position.abs();
+    // Layer 1: Atmospheric bands, bent by turbulence
+    let band_warp = noise.fbm(p * 3.0, Fractal::new(3, 2.0, 0.5)) * 0.15;
+    let band_intensity = ((p.y + band_warp) * 12.0).sin().abs();
     
     // Layer 2: Storm systems
-    let storm_noise = ((uv.x * 25.0 + time * 1.0).sin() * 
-                      (uv.y * 15.0).cos()).abs();
+    let (f1, _) = noise.worley(p * 5.0 + Vec3::new(time * 0.1, 0.0, 0.0));
+    let storm_noise = (1.0 - f1 * 2.0).max(0.0);
     
     // Layer 3: Great Red Spot equivalent
-    let spot_x = uv.x - 0.7;
-    let spot_y = uv.y - 0.4;
-    let spot_distance = (spot_x * spot_x + spot_y * spot_y).sqrt();
-    let great_spot = if spot_distance < 0.15 { 1.0 } else { 0.0 };
+    let spot_center = Vec3::new(0.8, -0.35, 0.48).normalize();
+    let spot_distance = (p - spot_center).magnitude() + noise.simplex(p * 10.0) * 0.03;
+    let great_spot = if spot_distance < 0.22 { 1.0 } else { 0.0 };
     
     // Layer 4: Atmospheric turbulence
-    let turbulence = ((uv.x * 30.0 + time * 2.0).sin() + 
-                     (uv.y * 20.0 + time * 1.5).cos()) * 0.5 + 0.5;
+    let flow = Vec3::new(time * 0.2, 0.0, time * 0.15);
+    let turbulence = noise.domain_warp(p * 4.0 + flow, 0.6, Fractal::new(3, 2.0, 0.5)) * 0.5 + 0.5;
     
     if great_spot > 0.0 {
-        Color::new(200, 100, 50) // Great Red Spot
+        LinearColor::from_srgb(200.0, 100.0, 50.0) // Great Red Spot
     } else {
         let base_orange = 200.0 * band_intensity;
         let base_brown = 150.0 * band_intensity;
         let white_storms = 100.0 * storm_noise;
         
-        let red = (base_orange + white_storms + 50.0 * turbulence).min(255.0) as u8;
-        let green = (base_brown + white_storms * 0.7 + 30.0 * turbulence).min(255.0) as u8;
-        let blue = (80.0 + white_storms * 0.3 + 20.0 * turbulence).min(255.0) as u8;
+        let red = base_orange + white_storms + 50.0 * turbulence;
+        let green = base_brown + white_storms * 0.7 + 30.0 * turbulence;
+        let blue = 80.0 + white_storms * 0.3 + 20.0 * turbulence;
         
-        Color::new(red, green, blue)
+        LinearColor::from_srgb(red, green, blue)
     }
 }
 
-pub fn ice_planet_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+pub fn ice_planet_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer ice planet shader
     
     // Layer 1: Ice crystal formations
-    let crystal_pattern = ((uv.x * 20.0).sin() * (uv.y * 15.0).cos()).abs();
+    let crystal_pattern = noise.simplex(p * 12.0).abs();
     
-    // Layer 2: Frozen ocean cracks
-    let crack_noise = ((uv.x * 40.0).sin() + (uv.y * 35.0).cos()).abs();
-    let has_cracks = crack_noise > 0.8;
+    // Layer 2: Frozen ocean cracks along the cell borders
+    let (f1, f2) = noise.worley(p * 6.0);
+    let has_cracks = f2 - f1 < 0.06;
     
     // Layer 3: Aurora-like subsurface glow
-    let aurora = ((uv.y * 8.0 + time * 2.0).sin() * 
-                 (uv.x * 6.0 + time * 1.5).cos()).abs();
+    let aurora = noise.fbm(p * 3.0 + Vec3::new(0.0, time * 0.4, 0.0), Fractal::new(3, 2.0, 0.5)) * 0.5 + 0.5;
     
     // Layer 4: Surface frost variation
-    let frost_variation = ((uv.x * 12.0).sin() + (uv.y * 10.0).cos()) * 0.5 + 0.5;
+    let frost_variation = noise.value(p * 8.0) * 0.5 + 0.5;
     
     if has_cracks {
-        let blue_glow = (150.0 + 50.0 * aurora) as u8;
-        Color::new(100, 150, blue_glow) // Deep ice cracks with subsurface ocean
+        let blue_glow = 150.0 + 50.0 * aurora;
+        LinearColor::from_srgb(100.0, 150.0, blue_glow) // Deep ice cracks with subsurface ocean
     } else {
-        let ice_brightness = (200.0 + 55.0 * crystal_pattern * frost_variation) as u8;
-        let blue_tint = (220.0 + 35.0 * aurora) as u8;
-        Color::new(ice_brightness, ice_brightness, blue_tint)
+        let ice_brightness = 200.0 + 55.0 * crystal_pattern * frost_variation;
+        let blue_tint = 220.0 + 35.0 * aurora;
+        LinearColor::from_srgb(ice_brightness, ice_brightness, blue_tint)
     }
 }
 
-pub fn volcanic_planet_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+pub fn volcanic_planet_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer volcanic planet shader
     
-    // Layer 1: Lava flows
-    let lava_flow = ((uv.x * 12.0 + time * 3.0).sin() * 
-                    (uv.y * 8.0 + time * 2.0).cos()).abs();
+    // Layer 1: Lava flows following ridged channels
+    let lava_flow = noise.ridged(p * 3.0 + Vec3::new(0.0, time * 0.05, 0.0), Fractal::new(4, 2.0, 0.5));
     
     // Layer 2: Volcanic rock formations
-    let rock_texture = ((uv.x * 25.0).sin() * (uv.y * 20.0).cos()).abs();
+    let (f1, _) = noise.worley(p * 10.0);
+    let rock_texture = f1.min(1.0);
     
-    // Layer 3: Active volcanic eruptions
-    let eruption_noise = ((uv.x * 15.0 + time * 5.0).sin() + 
-                         (uv.y * 10.0 + time * 4.0).cos()).abs();
-    let active_volcano = eruption_noise > 0.7;
+    // Layer 3: Active volcanic eruptions, pulsing
+    let eruption_noise = noise.simplex(p * 5.0 + Vec3::new(time * 0.5, 0.0, 0.0)) * 0.5 + 0.5;
+    let active_volcano = eruption_noise > 0.8;
     
     // Layer 4: Ash and smoke
-    let ash_clouds = ((uv.x * 30.0 + time * 1.0).sin() * 
-                     (uv.y * 25.0 + time * 0.8).cos()).abs();
+    let ash_drift = Vec3::new(time * 0.1, 0.0, time * 0.08);
+    let ash_clouds = noise.domain_warp(p * 2.0 + ash_drift, 1.0, Fractal::new(3, 2.0, 0.5)) * 0.5 + 0.5;
     
     if active_volcano {
-        let bright_red = (255.0 * eruption_noise) as u8;
-        let bright_yellow = (200.0 * eruption_noise) as u8;
-        Color::new(bright_red, bright_yellow, 50) // Bright lava
-    } else if lava_flow > 0.6 {
-        Color::new(200, 80, 20) // Lava flows
-    } else if ash_clouds > 0.5 {
-        let ash_level = (80.0 + 40.0 * ash_clouds) as u8;
-        Color::new(ash_level, ash_level, ash_level) // Ash clouds
+        let bright_red = 255.0 * eruption_noise;
+        let bright_yellow = 200.0 * eruption_noise;
+        LinearColor::from_srgb(bright_red, bright_yellow, 50.0) // Bright lava
+    } else if lava_flow > 0.75 {
+        LinearColor::from_srgb(200.0, 80.0, 20.0) // Lava flows
+    } else if ash_clouds > 0.62 {
+        let ash_level = 80.0 + 40.0 * ash_clouds;
+        LinearColor::from_srgb(ash_level, ash_level, ash_level) // Ash clouds
     } else {
         // Volcanic rock
-        let rock_red = (60.0 + 40.0 * rock_texture) as u8;
-        let rock_brown = (40.0 + 30.0 * rock_texture) as u8;
-        Color::new(rock_red, rock_brown, 20)
+        let rock_red = 60.0 + 40.0 * rock_texture;
+        let rock_brown = 40.0 + 30.0 * rock_texture;
+        LinearColor::from_srgb(rock_red, rock_brown, 20.0)
     }
 }
 
-pub fn ringed_planet_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
+pub fn ringed_planet_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let time = input.time;
+    let noise = noise::shared();
+
     // 4-layer ringed planet shader (like Saturn)
     
     // Layer 1: Atmospheric bands
-    let band_pattern = (uv.y * 15.0).sin().abs();
+    let band_warp = noise.fbm(p * 2.0, Fractal::new(3, 2.0, 0.5)) * 0.08;
+    let band_pattern = ((p.y + band_warp) * 15.0).sin().abs();
     
     // Layer 2: Hexagonal polar storm (like Saturn's north pole)
-    let hex_x = uv.x - 0.5;
-    let hex_y = uv.y - 0.8; // Near north pole
-    let hex_distance = (hex_x * hex_x + hex_y * hex_y).sqrt();
-    let hexagon_storm = if hex_distance < 0.1 { 1.0 } else { 0.0 };
+    let angle = p.z.atan2(p.x);
+    let hexagon_edge = (std::f32::consts::PI / 6.0).cos()
+        / ((angle.rem_euclid(std::f32::consts::PI / 3.0) - std::f32::consts::PI / 6.0).cos());
+    let hex_distance = (p.x * p.x + p.z * p.z).sqrt();
+    let hexagon_storm = if p.y > 0.0 && hex_distance < 0.3 * hexagon_edge { 1.0 } else { 0.0 };
     
-    // Layer 3: Wind patterns
-    let wind_streams = ((uv.x * 20.0 + time * 0.5).sin() * 
-                       (uv.y * 8.0).cos()).abs();
+    // Layer 3: Wind patterns stretched along the bands
+    let stretched = Vec3::new(p.x * 2.0, p.y * 12.0, p.z * 2.0);
+    let wind_streams = noise.fbm(stretched + Vec3::new(time * 0.1, 0.0, 0.0), Fractal::new(3, 2.0, 0.5)).abs();
     
     // Layer 4: Atmospheric composition colors
-    let methane_haze = ((uv.y * 10.0).cos() + 1.0) * 0.5;
+    let methane_haze = noise.value(p * 3.0) * 0.5 + 0.5;
     
     if hexagon_storm > 0.0 {
-        Color::new(100, 150, 200) // Hexagonal storm
+        LinearColor::from_srgb(100.0, 150.0, 200.0) // Hexagonal storm
     } else {
-        let yellow_base = (200.0 * band_pattern + 30.0 * methane_haze) as u8;
-        let brown_bands = (150.0 * band_pattern + 50.0 * wind_streams) as u8;
-        let blue_tint = (120.0 * methane_haze + 20.0 * wind_streams) as u8;
+        let yellow_base = 200.0 * band_pattern + 30.0 * methane_haze;
+        let brown_bands = 150.0 * band_pattern + 50.0 * wind_streams;
+        let blue_tint = 120.0 * methane_haze + 20.0 * wind_streams;
         
-        Color::new(yellow_base, brown_bands, blue_tint)
+        LinearColor::from_srgb(yellow_base, brown_bands, blue_tint)
     }
 }
 
-pub fn moon_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
-    // Simple moon shader with craters
-    let crater_noise = ((uv.x * 30.0).sin() * (uv.y * 25.0).cos()).abs();
-    let has_crater = crater_noise > 0.7;
-    
-    if has_crater {
-        Color::new(80, 80, 90) // Dark crater
-    } else {
-        let brightness = (150.0 + 50.0 * crater_noise) as u8;
-        Color::new(brightness, brightness, brightness - 10) // Gray moon surface
+pub fn moon_shader(input: &FragmentInput) -> LinearColor {
+    let p = input.position.normalize();
+    let noise = noise::shared();
+
+    // Layer 1: Bright highlands and dark maria
+    let maria = noise.fbm(p * 2.0, Fractal::new(3, 2.0, 0.5)) * 0.5 + 0.5;
+
+    // Layer 2: Crater floors around the Worley feature points
+    let (f1, _) = noise.worley(p * 6.0);
+    let crater = 1.0 - ((f1 - 0.15) / 0.2).clamp(0.0, 1.0);
+
+    // Layer 3: Raised crater rims
+    let rim = (1.0 - (f1 - 0.38).abs() / 0.06).max(0.0);
+
+    // Layer 4: Fine regolith grain
+    let grain = noise.value(p * 40.0) * 0.5 + 0.5;
+
+    let brightness = (110.0 + 70.0 * maria) * (1.0 - 0.35 * crater) + 40.0 * rim + 15.0 * grain;
+    LinearColor::from_srgb(brightness, brightness, brightness - 10.0)
+}
+
+// Look of one ring: base color of its particles, number of density bands
+// across its width and the opacity of the densest band. Ring shadows use
+// the same density profile.
+#[derive(Clone, Copy, Debug)]
+pub struct RingStyle {
+    pub color: Color,
+    pub bands: f32,
+    pub opacity: f32,
+}
+
+impl Default for RingStyle {
+    fn default() -> Self {
+        RingStyle {
+            color: Color::new(180, 160, 170),
+            bands: 4.0,
+            opacity: 0.6,
+        }
     }
 }
 
-pub fn ring_shader(
-    position: Vec3, 
-    normal: Vec3, 
-    uv: nalgebra_glm::Vec2, 
-    time: f32
-) -> Color {
-    // Ring particles shader
-    let distance_from_center = (position.x * position.x + position.z * position.z).sqrt();
-    let ring_density = ((distance_from_center * 50.0).sin()).abs();
-    
-    let particle_noise = ((position.x * 100.0 + time * 0.1).sin() * 
-                         (position.z * 80.0).cos()).abs();
-    
-    let alpha_equivalent = (100.0 * ring_density * particle_noise) as u8;
-    let ice_color = (150.0 + 50.0 * particle_noise) as u8;
-    
-    Color::new(ice_color, ice_color - 20, ice_color - 10)
-}
\ No newline at end of file
+impl RingStyle {
+    pub fn new(color: Color, bands: f32, opacity: f32) -> Self {
+        RingStyle { color, bands, opacity }
+    }
+
+    // Coverage of the banded density profile at `radial` (0 at the inner
+    // edge, 1 at the outer edge), in [opacity / 2, opacity]
+    pub fn density(&self, radial: f32) -> f32 {
+        self.opacity * (0.5 + 0.5 * (radial * self.bands * std::f32::consts::PI).sin().abs())
+    }
+}
+
+// Translucent ring particles in the given style; meant to be drawn with
+// `BlendMode::Alpha`. Patterns follow the ring's texture coordinates, so
+// they don't depend on the mesh size.
+pub struct RingShader(pub RingStyle);
+
+impl FragmentShader for RingShader {
+    fn shade(&self, input: &FragmentInput) -> LinearColor {
+        let style = &self.0;
+        let radial = input.uv.x;
+        let direction = input.position.normalize();
+        let time = input.time;
+
+        // Thin ringlets along the radius, slowly drifting clumps around the ring
+        let ringlets = noise::shared().fbm(Vec3::new(radial * 40.0, 0.0, 0.0), Fractal::default()) * 0.5 + 0.5;
+        let clumps = noise::shared().perlin(direction * 6.0 + Vec3::new(0.0, time * 0.1, 0.0)) * 0.5 + 0.5;
+        let particle_noise = 0.6 * ringlets + 0.4 * clumps;
+
+        let alpha = style.density(radial) * (0.7 + 0.3 * particle_noise);
+        let brightness = 0.75 + 0.5 * particle_noise;
+
+        (LinearColor::from(style.color) * brightness).with_alpha(alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_segments_share_their_boundary_vertices() {
+        let ring = Ring::new(1.0, 2.0, 12);
+
+        // An inner/outer pair per boundary, plus the pair closing the loop
+        assert_eq!(ring.mesh.vertices.len(), 2 * 13);
+        assert_eq!(ring.mesh.indices.len(), 12 * 6);
+
+        // Each inner vertex between two segments is used by three triangles
+        let uses = ring.mesh.indices.iter().filter(|&&index| index == 2 * 5).count();
+        assert_eq!(uses, 3);
+        assert!(ring.mesh.indices.iter().all(|&index| (index as usize) < ring.mesh.vertices.len()));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::color::LinearColor;
+use crate::framebuffer::Framebuffer;
+
+// A full-screen pass over the HDR target. Passes run after every body is
+// drawn and before `Framebuffer::resolve`, so they see linear, unclamped
+// light values.
+pub trait PostPass: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn apply(&self, framebuffer: &mut Framebuffer, time: f32);
+}
+
+// An ordered chain of post-processing passes. Passes can be switched off
+// at runtime without losing their place in the chain.
+#[derive(Default)]
+pub struct PostProcess {
+    stages: Vec<Stage>,
+}
+
+struct Stage {
+    pass: Box<dyn PostPass>,
+    enabled: bool,
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        PostProcess { stages: Vec::new() }
+    }
+
+    // Appends a pass; passes run in the order they were added
+    pub fn with(mut self, pass: impl PostPass + 'static) -> Self {
+        self.push(Box::new(pass));
+        self
+    }
+
+    pub fn push(&mut self, pass: Box<dyn PostPass>) {
+        self.stages.push(Stage { pass, enabled: true });
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|stage| stage.pass.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.stages.iter().any(|stage| stage.enabled && stage.pass.name() == name)
+    }
+
+    // Switches every pass called `name`; returns false if there is none
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for stage in self.stages.iter_mut().filter(|stage| stage.pass.name() == name) {
+            stage.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    pub fn apply(&self, framebuffer: &mut Framebuffer, time: f32) {
+        for stage in self.stages.iter().filter(|stage| stage.enabled) {
+            stage.pass.apply(framebuffer, time);
+        }
+    }
+}
+
+// Post-processing used by the interactive and headless renderers. FXAA
+// goes last so it also smooths the edges of everything before it.
+pub fn default_post_process() -> PostProcess {
+    PostProcess::new().with(Bloom::default()).with(Fxaa::default())
+}
+
+// Bright-pass followed by a separable Gaussian blur at half resolution,
+// added back on top of the image. Anything brighter than `threshold`
+// (in practice the star) bleeds light past its silhouette.
+#[derive(Clone, Copy, Debug)]
+pub struct Bloom {
+    pub threshold: f32, // Luminance where light starts to bloom
+    pub knee: f32,      // Width of the soft transition around the threshold
+    pub intensity: f32, // Strength of the blurred light added back
+    pub radius: f32,    // Blur sigma as a fraction of the framebuffer height
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.8,
+            radius: 0.03,
+        }
+    }
+}
+
+impl PostPass for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn apply(&self, framebuffer: &mut Framebuffer, _time: f32) {
+        if framebuffer.width < 2 || framebuffer.height < 2 || self.intensity <= 0.0 {
+            return;
+        }
+
+        let bright = self.bright_pass(framebuffer);
+        let sigma = (self.radius * framebuffer.height as f32 * 0.5).max(0.5);
+        let kernel = gaussian_kernel(sigma);
+        let blurred = bright.blur_rows(&kernel).blur_columns(&kernel);
+
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                let index = y * framebuffer.width + x;
+                let glow = blurred.sample(x as f32 * 0.5 - 0.25, y as f32 * 0.5 - 0.25);
+                framebuffer.color[index] = framebuffer.color[index] + glow * self.intensity;
+            }
+        }
+    }
+}
+
+impl Bloom {
+    // Share of a pixel's light that blooms, with a quadratic knee so
+    // pixels don't pop in and out as they cross the threshold
+    fn contribution(&self, luminance: f32) -> f32 {
+        let soft = (luminance - self.threshold + self.knee).clamp(0.0, 2.0 * self.knee);
+        let soft = soft * soft / (4.0 * self.knee + 1e-5);
+        soft.max(luminance - self.threshold) / luminance.max(1e-5)
+    }
+
+    // Bright parts of the image, box-filtered down to half resolution
+    fn bright_pass(&self, framebuffer: &Framebuffer) -> Image {
+        let width = framebuffer.width / 2;
+        let height = framebuffer.height / 2;
+        let mut image = Image::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = LinearColor::black();
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let color = framebuffer.color[(2 * y + dy) * framebuffer.width + 2 * x + dx];
+                    sum = sum + color * self.contribution(color.luminance());
+                }
+                image.pixels[y * width + x] = sum * 0.25;
+            }
+        }
+
+        image
+    }
+}
+
+// Fast approximate anti-aliasing: finds edges from luma contrast, walks
+// along each edge to its ends and blends every pixel with its neighbor
+// across the edge according to where it sits on the edge. A simplified
+// take on Timothy Lottes' FXAA 3.11 quality preset.
+#[derive(Clone, Copy, Debug)]
+pub struct Fxaa {
+    pub edge_threshold: f32,     // Contrast needed, relative to the brightest neighbor
+    pub edge_threshold_min: f32, // Contrast needed in dark areas
+    pub subpixel: f32,           // Strength of the blur on single-pixel features
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Fxaa {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            subpixel: 0.75,
+        }
+    }
+}
+
+// Pixels walked along an edge in each direction looking for its end
+const FXAA_SEARCH_STEPS: i32 = 12;
+
+impl PostPass for Fxaa {
+    fn name(&self) -> &'static str {
+        "fxaa"
+    }
+
+    fn apply(&self, framebuffer: &mut Framebuffer, _time: f32) {
+        let (width, height) = (framebuffer.width as i32, framebuffer.height as i32);
+        if width < 3 || height < 3 {
+            return;
+        }
+
+        let luma: Vec<f32> = framebuffer.color.iter().map(|&color| perceptual_luma(color)).collect();
+        let source = framebuffer.color.clone();
+        let at = |x: i32, y: i32| luma[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize];
+
+        for y in 0..height {
+            for x in 0..width {
+                let center = at(x, y);
+                let (north, south, east, west) = (at(x, y - 1), at(x, y + 1), at(x + 1, y), at(x - 1, y));
+
+                let brightest = center.max(north).max(south).max(east).max(west);
+                let darkest = center.min(north).min(south).min(east).min(west);
+                let range = brightest - darkest;
+                if range < self.edge_threshold_min.max(brightest * self.edge_threshold) {
+                    continue;
+                }
+
+                let (north_west, north_east) = (at(x - 1, y - 1), at(x + 1, y - 1));
+                let (south_west, south_east) = (at(x - 1, y + 1), at(x + 1, y + 1));
+
+                // Sub-pixel aliasing: how much the center differs from its neighborhood
+                let average = (2.0 * (north + south + east + west) + north_west + north_east + south_west + south_east) / 12.0;
+                let contrast = ((average - center).abs() / range).clamp(0.0, 1.0);
+                let smooth = contrast * contrast * (3.0 - 2.0 * contrast);
+                let subpixel_blend = smooth * smooth * self.subpixel;
+
+                // Edge orientation from the second derivatives across rows and columns
+                let horizontal = 2.0 * (north + south - 2.0 * center).abs()
+                    + (north_east + south_east - 2.0 * east).abs()
+                    + (north_west + south_west - 2.0 * west).abs();
+                let vertical = 2.0 * (east + west - 2.0 * center).abs()
+                    + (north_east + north_west - 2.0 * north).abs()
+                    + (south_east + south_west - 2.0 * south).abs();
+                let is_horizontal = horizontal >= vertical;
+
+                // The neighbor across the edge: the side with the larger gradient
+                let (positive, negative) = if is_horizontal { (south, north) } else { (east, west) };
+                let (step, opposite) = if (positive - center).abs() >= (negative - center).abs() {
+                    (1, positive)
+                } else {
+                    (-1, negative)
+                };
+                let gradient = (opposite - center).abs() / 4.0;
+                let edge_luma = (center + opposite) * 0.5;
+
+                // Luma on the edge line between this row (column) and the opposite one
+                let (along_x, along_y, across_x, across_y) = if is_horizontal { (1, 0, 0, step) } else { (0, 1, step, 0) };
+                let edge_at = |i: i32| {
+                    let (ex, ey) = (x + along_x * i, y + along_y * i);
+                    (at(ex, ey) + at(ex + across_x, ey + across_y)) * 0.5 - edge_luma
+                };
+
+                let search = |direction: i32| {
+                    for i in 1..=FXAA_SEARCH_STEPS {
+                        let delta = edge_at(i * direction);
+                        if delta.abs() >= gradient {
+                            return (i, delta);
+                        }
+                    }
+                    (FXAA_SEARCH_STEPS, edge_at(FXAA_SEARCH_STEPS * direction))
+                };
+                let (distance_positive, end_positive) = search(1);
+                let (distance_negative, end_negative) = search(-1);
+
+                // Blend only towards the end where the edge actually changes side
+                let (distance, end) = if distance_positive < distance_negative {
+                    (distance_positive, end_positive)
+                } else {
+                    (distance_negative, end_negative)
+                };
+                let edge_length = (distance_positive + distance_negative) as f32;
+                let edge_blend = if (end < 0.0) != (center < edge_luma) {
+                    0.5 - distance as f32 / edge_length
+                } else {
+                    0.0
+                };
+
+                let blend = edge_blend.max(subpixel_blend);
+                if blend <= 0.0 {
+                    continue;
+                }
+
+                let index = (y * width + x) as usize;
+                let neighbor = ((y + across_y).clamp(0, height - 1) * width + (x + across_x).clamp(0, width - 1)) as usize;
+                framebuffer.color[index] = source[index] * (1.0 - blend) + source[neighbor] * blend;
+            }
+        }
+    }
+}
+
+// Luma on a compressed, roughly perceptual scale, so edges against the
+// HDR star get the same treatment as edges between dim surfaces
+fn perceptual_luma(color: LinearColor) -> f32 {
+    let luminance = color.luminance().max(0.0);
+    (luminance / (1.0 + luminance)).sqrt()
+}
+
+// Normalized weights from the center tap outwards
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let half_width = (3.0 * sigma).ceil() as usize;
+    let weights: Vec<f32> = (0..=half_width)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+// Scratch HDR image for the blur passes
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<LinearColor>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Image { width, height, pixels: vec![LinearColor::black(); width * height] }
+    }
+
+    // Pixel with coordinates clamped to the edges
+    fn at(&self, x: isize, y: isize) -> LinearColor {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    fn blur(&self, kernel: &[f32], (step_x, step_y): (isize, isize)) -> Image {
+        let mut output = Image::new(self.width, self.height);
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let mut sum = self.at(x, y) * kernel[0];
+                for (i, &weight) in kernel.iter().enumerate().skip(1) {
+                    let offset = i as isize;
+                    sum = sum
+                        + self.at(x - offset * step_x, y - offset * step_y) * weight
+                        + self.at(x + offset * step_x, y + offset * step_y) * weight;
+                }
+                output.pixels[y as usize * self.width + x as usize] = sum;
+            }
+        }
+        output
+    }
+
+    fn blur_rows(&self, kernel: &[f32]) -> Image {
+        self.blur(kernel, (1, 0))
+    }
+
+    fn blur_columns(&self, kernel: &[f32]) -> Image {
+        self.blur(kernel, (0, 1))
+    }
+
+    // Bilinear sample in pixel coordinates
+    fn sample(&self, x: f32, y: f32) -> LinearColor {
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        let top = self.at(x0, y0) * (1.0 - tx) + self.at(x0 + 1, y0) * tx;
+        let bottom = self.at(x0, y0 + 1) * (1.0 - tx) + self.at(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Scale(f32);
+    struct Offset(f32);
+
+    impl PostPass for Scale {
+        fn name(&self) -> &'static str {
+            "scale"
+        }
+
+        fn apply(&self, framebuffer: &mut Framebuffer, _time: f32) {
+            for color in framebuffer.color.iter_mut() {
+                *color = *color * self.0;
+            }
+        }
+    }
+
+    impl PostPass for Offset {
+        fn name(&self) -> &'static str {
+            "offset"
+        }
+
+        fn apply(&self, framebuffer: &mut Framebuffer, _time: f32) {
+            for color in framebuffer.color.iter_mut() {
+                *color = *color + LinearColor::new(self.0, self.0, self.0);
+            }
+        }
+    }
+
+    const SIZE: usize = 64;
+
+    fn framebuffer_with_bright_square(brightness: f32) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.clear();
+        for y in 28..36 {
+            for x in 28..36 {
+                framebuffer.color[y * SIZE + x] = LinearColor::new(brightness, brightness, brightness);
+            }
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn bloom_spreads_bright_light_past_its_edges() {
+        let mut framebuffer = framebuffer_with_bright_square(8.0);
+        Bloom { radius: 0.05, ..Bloom::default() }.apply(&mut framebuffer, 0.0);
+
+        let near = framebuffer.color[32 * SIZE + 38].r;
+        let far = framebuffer.color[32 * SIZE + 60].r;
+        assert!(near > 0.05, "no glow next to the square: {}", near);
+        assert!(far < near);
+        assert!(framebuffer.color[32 * SIZE + 32].r >= 8.0);
+    }
+
+    #[test]
+    fn bloom_leaves_dim_images_alone() {
+        let mut framebuffer = framebuffer_with_bright_square(0.3);
+        let before = framebuffer.color.clone();
+        Bloom::default().apply(&mut framebuffer, 0.0);
+        assert_eq!(framebuffer.color, before);
+    }
+
+    #[test]
+    fn fxaa_smooths_staircase_edges_only() {
+        // A shallow dark/bright slope, drawn without coverage: a staircase
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let bright = (y as f32) < 20.0 + x as f32 * 0.25;
+                framebuffer.color[y * SIZE + x] = LinearColor::new(1.0, 1.0, 1.0) * if bright { 1.0 } else { 0.0 };
+            }
+        }
+        let before = framebuffer.color.clone();
+        Fxaa::default().apply(&mut framebuffer, 0.0);
+
+        let changed: Vec<usize> = (0..SIZE * SIZE).filter(|&i| framebuffer.color[i] != before[i]).collect();
+        assert!(!changed.is_empty());
+
+        // Only pixels next to the edge are touched, and only with blends
+        for &i in &changed {
+            let (x, y) = ((i % SIZE) as f32, (i / SIZE) as f32);
+            assert!((y - (20.0 + x * 0.25)).abs() < 2.0, "pixel {} {} is far from the edge", x, y);
+            assert!(framebuffer.color[i].r > 0.0 && framebuffer.color[i].r < 1.0);
+        }
+    }
+
+    #[test]
+    fn disabled_passes_keep_their_place_but_do_not_run() {
+        let mut chain = PostProcess::new().with(Scale(2.0)).with(Offset(1.0));
+        assert!(chain.set_enabled("scale", false));
+        assert!(!chain.set_enabled("missing", false));
+        assert!(!chain.is_enabled("scale"));
+
+        // Scaling only shows on a lit pixel
+        let gray = LinearColor::new(3.0, 3.0, 3.0);
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.color[0] = gray;
+        chain.apply(&mut framebuffer, 0.0);
+        assert_eq!(framebuffer.color[0], LinearColor::new(4.0, 4.0, 4.0));
+        assert_eq!(chain.names(), ["scale", "offset"]);
+
+        // Re-enabled, it runs before the offset again
+        assert!(chain.set_enabled("scale", true));
+        assert!(chain.is_enabled("scale"));
+        framebuffer.color[0] = gray;
+        chain.apply(&mut framebuffer, 0.0);
+        assert_eq!(framebuffer.color[0], LinearColor::new(7.0, 7.0, 7.0));
+    }
+
+    #[test]
+    fn passes_run_in_the_order_they_were_added() {
+        let chain = PostProcess::new().with(Scale(2.0)).with(Offset(1.0));
+        assert_eq!(chain.names(), ["scale", "offset"]);
+
+        let mut framebuffer = Framebuffer::new(1, 1);
+        chain.apply(&mut framebuffer, 0.0);
+        assert_eq!(framebuffer.color[0], LinearColor::new(1.0, 1.0, 1.0));
+
+        let mut framebuffer = Framebuffer::new(1, 1);
+        PostProcess::new().with(Offset(1.0)).with(Scale(2.0)).apply(&mut framebuffer, 0.0);
+        assert_eq!(framebuffer.color[0], LinearColor::new(2.0, 2.0, 2.0));
+    }
+}
@@ -0,0 +1,563 @@
+use nalgebra_glm::{Vec3, Mat4};
+use crate::framebuffer::{BlendMode, Framebuffer};
+use crate::tiles::{rasterize_tiled, DrawCall, ScreenTriangle};
+use crate::vertex::Vertex;
+use crate::mesh::Mesh;
+use crate::shaders::{vertex_shader, viewport_transform, FragmentShader, SpaceshipShader, Uniforms};
+use crate::triangle::{triangle_with_shader, rasterize_triangle, is_culled, CullMode, FrontFace};
+use crate::clipping::clip_triangle;
+use crate::camera::Camera;
+use crate::lighting::PointLight;
+use crate::shadows::Occluders;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RasterMode {
+    // Gathers every fragment of a mesh before writing it, as the renderer
+    // did before streaming; only kept as a reference for benchmarks
+    Collected,
+    // Single-threaded rasterizer with early-z
+    Streaming,
+    // Triangles are queued for the whole frame, binned into tiles of
+    // `tile_height` rows and rasterized on `threads` worker threads
+    Tiled { threads: usize, tile_height: usize },
+}
+
+impl RasterMode {
+    // Tiled mode using every available core
+    pub fn tiled() -> Self {
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        RasterMode::Tiled { threads, tile_height: 32 }
+    }
+}
+
+// Front end for drawing meshes with the selected raster mode. Immediate
+// modes write opaque meshes to the framebuffer on every draw; the tiled
+// mode only does the geometry work and rasterizes everything in `finish`,
+// which is why it borrows the shaders of the meshes drawn during the frame.
+// Meshes with a blending shader are always held back for the transparent
+// pass at the end of `finish`.
+pub struct Renderer<'a> {
+    pub mode: RasterMode,
+    draws: Vec<DrawCall<'a>>,
+    queue: Vec<ScreenTriangle>,
+    transparent_draws: Vec<DrawCall<'a>>,
+    transparent_queue: Vec<ScreenTriangle>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(mode: RasterMode) -> Self {
+        Renderer {
+            mode,
+            draws: Vec::new(),
+            queue: Vec::new(),
+            transparent_draws: Vec::new(),
+            transparent_queue: Vec::new(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        uniforms: &Uniforms,
+        mesh: &Mesh,
+        shader: &'a dyn FragmentShader,
+        time: f32,
+        cull_mode: CullMode,
+        front_face: FrontFace,
+    ) {
+        if shader.blend_mode() != BlendMode::Opaque {
+            queue_draw(&mut self.transparent_draws, &mut self.transparent_queue, uniforms, mesh, shader, time, cull_mode, front_face);
+            return;
+        }
+
+        match self.mode {
+            RasterMode::Collected => {
+                render_with_shader_collected(framebuffer, uniforms, mesh, shader, time, cull_mode, front_face)
+            }
+            RasterMode::Streaming => {
+                render_with_shader(framebuffer, uniforms, mesh, shader, time, cull_mode, front_face)
+            }
+            RasterMode::Tiled { .. } => {
+                queue_draw(&mut self.draws, &mut self.queue, uniforms, mesh, shader, time, cull_mode, front_face);
+            }
+        }
+    }
+
+    // Complete the frame: the tiled mode rasterizes the queued opaque
+    // meshes, then every mode blends the transparent ones on top and the
+    // samples of a multisampled framebuffer are resolved
+    pub fn finish(&mut self, framebuffer: &mut Framebuffer) {
+        if let RasterMode::Tiled { threads, tile_height } = self.mode {
+            rasterize_tiled(framebuffer, &self.draws, &self.queue, threads, tile_height);
+            self.draws.clear();
+            self.queue.clear();
+        }
+
+        // Transparent Pass - blending isn't commutative, so meshes are
+        // sorted back to front; triangles of one mesh keep their order
+        let order = back_to_front(&self.transparent_draws);
+        self.transparent_queue.sort_by_key(|triangle| order[triangle.draw]);
+
+        match self.mode {
+            RasterMode::Tiled { threads, tile_height } => {
+                rasterize_tiled(framebuffer, &self.transparent_draws, &self.transparent_queue, threads, tile_height);
+            }
+            RasterMode::Collected | RasterMode::Streaming => {
+                let mut tile = framebuffer.full_tile();
+                for triangle in &self.transparent_queue {
+                    let draw = &self.transparent_draws[triangle.draw];
+                    let [v1, v2, v3] = &triangle.vertices;
+                    rasterize_triangle(&mut tile, v1, v2, v3, draw.shader, &draw.uniforms, draw.time);
+                }
+            }
+        }
+        self.transparent_draws.clear();
+        self.transparent_queue.clear();
+
+        framebuffer.resolve_samples();
+    }
+}
+
+// Runs the geometry stages of a mesh and queues its screen triangles
+#[allow(clippy::too_many_arguments)]
+fn queue_draw<'a>(
+    draws: &mut Vec<DrawCall<'a>>,
+    queue: &mut Vec<ScreenTriangle>,
+    uniforms: &Uniforms,
+    mesh: &Mesh,
+    shader: &'a dyn FragmentShader,
+    time: f32,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+) {
+    let draw = draws.len();
+    draws.push(DrawCall {
+        shader,
+        uniforms: *uniforms,
+        time,
+    });
+
+    process_geometry(uniforms, mesh, cull_mode, front_face, |v1, v2, v3| {
+        queue.push(ScreenTriangle {
+            vertices: [v1.clone(), v2.clone(), v3.clone()],
+            draw,
+        });
+    });
+}
+
+// Rank of each draw when sorted by the distance from the camera to its
+// model origin, farthest first
+fn back_to_front(draws: &[DrawCall]) -> Vec<usize> {
+    let distance = |draw: &DrawCall| {
+        let origin = draw.uniforms.model_matrix.column(3).xyz();
+        (origin - draw.uniforms.camera_position).magnitude()
+    };
+
+    let mut sorted: Vec<usize> = (0..draws.len()).collect();
+    sorted.sort_by(|&a, &b| distance(&draws[b]).total_cmp(&distance(&draws[a])));
+
+    let mut rank = vec![0; draws.len()];
+    for (position, &draw) in sorted.iter().enumerate() {
+        rank[draw] = position;
+    }
+    rank
+}
+
+pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0,  0.0,    0.0,   0.0,
+        0.0,  cos_x, -sin_x, 0.0,
+        0.0,  sin_x,  cos_x, 0.0,
+        0.0,  0.0,    0.0,   1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y,  0.0,  sin_y, 0.0,
+        0.0,    1.0,  0.0,   0.0,
+        -sin_y, 0.0,  cos_y, 0.0,
+        0.0,    0.0,  0.0,   1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0,
+        sin_z,  cos_z, 0.0, 0.0,
+        0.0,    0.0,  1.0, 0.0,
+        0.0,    0.0,  0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale, 0.0,   0.0,   translation.x,
+        0.0,   scale, 0.0,   translation.y,
+        0.0,   0.0,   scale, translation.z,
+        0.0,   0.0,   0.0,   1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+    Mat4::new(
+        width / 2.0, 0.0,           0.0, width / 2.0,
+        0.0,         -height / 2.0, 0.0, height / 2.0,
+        0.0,         0.0,           0.5, 0.5,
+        0.0,         0.0,           0.0, 1.0,
+    )
+}
+
+// Camera matrices shared by every body of a frame (identity model matrix)
+pub fn create_frame_uniforms(camera: &Camera, width: usize, height: usize) -> Uniforms {
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix: camera.view_matrix(),
+        projection_matrix: camera.projection_matrix(width as f32 / height as f32),
+        viewport_matrix: create_viewport_matrix(width as f32, height as f32),
+        camera_position: camera.eye,
+        light: PointLight::default(),
+        occluders: Occluders::default(),
+    }
+}
+
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, mesh: &Mesh, cull_mode: CullMode) {
+    render_with_shader(framebuffer, uniforms, mesh, &SpaceshipShader, 0.0, cull_mode, FrontFace::Clockwise)
+}
+
+pub fn render_with_shader(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &Mesh,
+    shader: &dyn FragmentShader,
+    time: f32,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+) {
+    // Rasterization Stage - shade depth-tested fragments directly into the framebuffer
+    let mut tile = framebuffer.full_tile();
+    process_geometry(uniforms, mesh, cull_mode, front_face, |v1, v2, v3| {
+        rasterize_triangle(&mut tile, v1, v2, v3, shader, uniforms, time);
+    });
+}
+
+// Vertex shading, clipping, viewport mapping and culling. Every surviving
+// screen-space triangle is handed to `emit` in submission order.
+fn process_geometry(
+    uniforms: &Uniforms,
+    mesh: &Mesh,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    mut emit: impl FnMut(&Vertex, &Vertex, &Vertex),
+) {
+    // Vertex Shader Stage - each unique vertex is transformed once
+    let transformed_vertices: Vec<Vertex> = mesh.vertices
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    // Reused by every triangle of the mesh
+    let mut polygon = Vec::with_capacity(9);
+    let mut scratch = Vec::with_capacity(9);
+
+    // Primitive Assembly Stage - gather triangles through the index buffer
+    for tri in mesh.indices.chunks_exact(3) {
+        let v1 = &transformed_vertices[tri[0] as usize];
+        let v2 = &transformed_vertices[tri[1] as usize];
+        let v3 = &transformed_vertices[tri[2] as usize];
+
+        // Clipping Stage - cut against the view frustum in clip space
+        clip_triangle(v1, v2, v3, &mut polygon, &mut scratch);
+
+        // Perspective division and viewport mapping of the surviving vertices
+        for vertex in polygon.iter_mut() {
+            viewport_transform(vertex, uniforms);
+        }
+
+        // Fan-triangulate the clipped polygon
+        for i in 1..polygon.len().saturating_sub(1) {
+            let (v1, v2, v3) = (&polygon[0], &polygon[i], &polygon[i + 1]);
+
+            // Face Culling Stage
+            if is_culled(v1, v2, v3, cull_mode, front_face) {
+                continue;
+            }
+
+            emit(v1, v2, v3);
+        }
+    }
+}
+
+// Gathers every fragment before writing it. Clipping and triangle setup
+// are the current ones, so only the collecting itself differs.
+pub fn render_with_shader_collected(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &Mesh,
+    shader: &dyn FragmentShader,
+    time: f32,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+) {
+    // Vertex Shader Stage - on the expanded triangle list, as before indexed meshes
+    let vertex_array = mesh.to_vertex_array();
+    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
+    for vertex in &vertex_array {
+        let transformed = vertex_shader(vertex, uniforms);
+        transformed_vertices.push(transformed);
+    }
+
+    // Primitive Assembly Stage - manually iterate through faces
+    let mut triangles = Vec::new();
+    for i in (0..transformed_vertices.len()).step_by(3) {
+        if i + 2 < transformed_vertices.len() {
+            let mut polygon = Vec::new();
+            let mut scratch = Vec::new();
+            clip_triangle(
+                &transformed_vertices[i],
+                &transformed_vertices[i + 1],
+                &transformed_vertices[i + 2],
+                &mut polygon,
+                &mut scratch,
+            );
+
+            for vertex in polygon.iter_mut() {
+                viewport_transform(vertex, uniforms);
+            }
+
+            for k in 1..polygon.len().saturating_sub(1) {
+                let tri = [polygon[0].clone(), polygon[k].clone(), polygon[k + 1].clone()];
+                if !is_culled(&tri[0], &tri[1], &tri[2], cull_mode, front_face) {
+                    triangles.push(tri);
+                }
+            }
+        }
+    }
+
+    // Rasterization Stage - draw all triangles
+    let mut fragments = Vec::new();
+    for tri in &triangles {
+        fragments.extend(triangle_with_shader(&tri[0], &tri[1], &tri[2], shader, uniforms, time));
+    }
+
+    // Fragment Processing Stage
+    for fragment in fragments {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        if x < framebuffer.width && y < framebuffer.height {
+            framebuffer.fragment(x, y, fragment.color, fragment.depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec4;
+    use std::f32::consts::PI;
+    use crate::sphere::Sphere;
+    use crate::planets::{Ring, star_shader};
+    use crate::scene::{Scene, default_camera};
+    use crate::tonemap::ToneMapping;
+    use crate::color::LinearColor;
+    use crate::shaders::{Blended, FragmentInput};
+
+    const WIDTH: usize = 200;
+    const HEIGHT: usize = 200;
+
+    fn test_uniforms() -> Uniforms {
+        let camera = Camera::new(
+            Vec3::new(0.0, 150.0, 300.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            PI / 3.0,
+            1.0,
+            1000.0,
+        );
+        create_frame_uniforms(&camera, WIDTH, HEIGHT)
+    }
+
+    // Screen pixel and depth of a world-space point
+    fn project(uniforms: &Uniforms, point: Vec3) -> (usize, usize, f32) {
+        let clip = uniforms.projection_matrix * uniforms.view_matrix * point.push(1.0);
+        let screen = uniforms.viewport_matrix * Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+        (screen.x as usize, screen.y as usize, screen.z)
+    }
+
+    #[test]
+    fn ring_passing_through_sphere_splits_cleanly() {
+        let frame_uniforms = test_uniforms();
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear();
+
+        // Sphere of radius 50 at the origin, shaded red-dominant by the star shader
+        let sphere = Sphere::new(1.0, 32, 32);
+        let uniforms = Uniforms {
+            model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 50.0, Vec3::new(0.0, 0.0, 0.0)),
+            ..frame_uniforms
+        };
+        render_with_shader(&mut framebuffer, &uniforms, sphere.get_mesh(), &star_shader, 0.0, CullMode::Back, FrontFace::Clockwise);
+
+        // Flat ring crossing the sphere's equator, shaded blue-dominant by the fallback path
+        let ring = Ring::new(20.0, 100.0, 128);
+        render(&mut framebuffer, &frame_uniforms, &ring.mesh, CullMode::None);
+
+        framebuffer.resolve(&ToneMapping::default());
+        let is_ring = |hex: u32| (hex & 0xFF) > (hex >> 16) & 0xFF;
+
+        // In front of the sphere the ring wins, with the depth of the ring itself
+        let (x, y, depth) = project(&frame_uniforms, Vec3::new(0.0, 0.0, 70.0));
+        let index = y * WIDTH + x;
+        assert!(is_ring(framebuffer.buffer[index]));
+        assert!((framebuffer.zbuffer[index] - depth).abs() < 1e-3);
+
+        // Behind the sphere the ring is hidden by the planet surface
+        let (x, y, _) = project(&frame_uniforms, Vec3::new(0.0, 0.0, -60.0));
+        assert!(!is_ring(framebuffer.buffer[y * WIDTH + x]));
+
+        // Inside the sphere the ring never shows through
+        let (x, y, _) = project(&frame_uniforms, Vec3::new(0.0, 0.0, 30.0));
+        assert!(!is_ring(framebuffer.buffer[y * WIDTH + x]));
+    }
+
+    #[test]
+    fn transparent_meshes_blend_over_opaque_ones_drawn_later() {
+        let frame_uniforms = test_uniforms();
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear();
+
+        let red = |_: &FragmentInput| LinearColor::new(1.0, 0.0, 0.0);
+        let half_blue = Blended(|_: &FragmentInput| LinearColor::rgba(0.0, 0.0, 1.0, 0.5), BlendMode::Alpha);
+
+        // The translucent ring is submitted first but only drawn in `finish`
+        let sphere = Sphere::new(1.0, 32, 32);
+        let ring = Ring::new(20.0, 100.0, 128);
+        let sphere_uniforms = Uniforms {
+            model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 50.0, Vec3::new(0.0, 0.0, 0.0)),
+            ..frame_uniforms
+        };
+        let mut renderer = Renderer::new(RasterMode::Streaming);
+        renderer.draw(&mut framebuffer, &frame_uniforms, &ring.mesh, &half_blue, 0.0, CullMode::None, FrontFace::Clockwise);
+        renderer.draw(&mut framebuffer, &sphere_uniforms, sphere.get_mesh(), &red, 0.0, CullMode::Back, FrontFace::Clockwise);
+        renderer.finish(&mut framebuffer);
+
+        // In front of the sphere both colors mix, and the depth stays the sphere's
+        let (x, y, ring_depth) = project(&frame_uniforms, Vec3::new(0.0, 0.0, 70.0));
+        let index = y * WIDTH + x;
+        let pixel = framebuffer.color[index];
+        assert!((pixel.r - 0.5).abs() < 1e-4 && (pixel.b - 0.5).abs() < 1e-4, "{:?}", pixel);
+        assert!(framebuffer.zbuffer[index] > ring_depth);
+
+        // Behind the sphere the ring is still hidden
+        let (x, y, _) = project(&frame_uniforms, Vec3::new(0.0, 0.0, -60.0));
+        assert_eq!(framebuffer.color[y * WIDTH + x], LinearColor::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn blend_modes_combine_with_the_framebuffer() {
+        let destination = LinearColor::new(0.5, 0.5, 0.5);
+        let source = LinearColor::rgba(1.0, 0.0, 0.2, 0.25);
+
+        assert_eq!(BlendMode::Opaque.blend(destination, source), LinearColor::new(1.0, 0.0, 0.2));
+        assert_eq!(BlendMode::Alpha.blend(destination, source), LinearColor::new(0.625, 0.375, 0.425));
+        assert_eq!(BlendMode::Additive.blend(destination, source), LinearColor::new(0.75, 0.5, 0.55));
+        assert_eq!(BlendMode::Multiply.blend(destination, source), LinearColor::new(0.5, 0.375, 0.4));
+    }
+
+    fn render_default_scene(mode: RasterMode) -> Framebuffer {
+        render_default_scene_multisampled(mode, 1)
+    }
+
+    fn render_default_scene_multisampled(mode: RasterMode, samples: usize) -> Framebuffer {
+        let mut scene = Scene::solar_system();
+        scene.update(0.5);
+        let frame_uniforms = create_frame_uniforms(&default_camera(), WIDTH, HEIGHT);
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.set_samples(samples);
+        framebuffer.clear();
+        scene.render(&mut framebuffer, &mut Renderer::new(mode), &frame_uniforms, 1.0);
+        framebuffer
+    }
+
+    #[test]
+    fn streaming_path_matches_collected_path() {
+        let streamed = render_default_scene(RasterMode::Streaming);
+        let collected = render_default_scene(RasterMode::Collected);
+
+        assert_eq!(streamed.color, collected.color);
+        assert_eq!(streamed.zbuffer, collected.zbuffer);
+    }
+
+    #[test]
+    fn tiled_path_is_bit_identical_to_single_threaded() {
+        let streamed = render_default_scene(RasterMode::Streaming);
+
+        // Odd tile heights leave a partial last tile
+        for (threads, tile_height) in [(1, 600), (4, 16), (3, 7), (8, 1)] {
+            let tiled = render_default_scene(RasterMode::Tiled { threads, tile_height });
+            assert_eq!(streamed.color, tiled.color);
+            assert_eq!(streamed.zbuffer, tiled.zbuffer);
+        }
+    }
+
+    #[test]
+    fn tiled_multisampling_is_bit_identical_to_single_threaded() {
+        let streamed = render_default_scene_multisampled(RasterMode::Streaming, 4);
+        let tiled = render_default_scene_multisampled(RasterMode::Tiled { threads: 4, tile_height: 7 }, 4);
+        assert_eq!(streamed.color, tiled.color);
+        assert_eq!(streamed.zbuffer, tiled.zbuffer);
+    }
+
+    #[test]
+    fn multisampling_blends_silhouette_pixels() {
+        let white = |_: &FragmentInput| LinearColor::new(1.0, 1.0, 1.0);
+        let sphere = Sphere::new(1.0, 32, 32);
+        let uniforms = Uniforms {
+            model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 50.0, Vec3::new(0.0, 0.0, 0.0)),
+            ..test_uniforms()
+        };
+
+        let partial_pixels = |samples: usize| {
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.set_samples(samples);
+            framebuffer.clear();
+
+            let mut renderer = Renderer::new(RasterMode::Streaming);
+            renderer.draw(&mut framebuffer, &uniforms, sphere.get_mesh(), &white, 0.0, CullMode::Back, FrontFace::Clockwise);
+            renderer.finish(&mut framebuffer);
+            framebuffer.color.iter().filter(|color| color.r > 0.0 && color.r < 1.0).count()
+        };
+
+        assert_eq!(partial_pixels(1), 0);
+        for samples in [2, 4, 8] {
+            // The silhouette is roughly 160 pixels long
+            assert!(partial_pixels(samples) > 40, "{}x MSAA left the silhouette aliased", samples);
+        }
+    }
+
+    #[test]
+    fn closed_mesh_covers_each_pixel_once() {
+        // Front faces of a sphere tile its silhouette: with additive white
+        // every pixel is either untouched or drawn exactly once
+        let shader = Blended(|_: &FragmentInput| LinearColor::new(1.0, 1.0, 1.0), BlendMode::Additive);
+        let sphere = Sphere::new(1.0, 32, 32);
+        let uniforms = Uniforms {
+            model_matrix: create_model_matrix(Vec3::new(3.7, -2.2, 0.0), 61.3, Vec3::new(0.4, 1.1, 0.0)),
+            ..test_uniforms()
+        };
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear();
+        let mut renderer = Renderer::new(RasterMode::Tiled { threads: 4, tile_height: 7 });
+        renderer.draw(&mut framebuffer, &uniforms, sphere.get_mesh(), &shader, 0.0, CullMode::Back, FrontFace::Clockwise);
+        renderer.finish(&mut framebuffer);
+
+        let covered = framebuffer.color.iter().filter(|color| color.r > 0.0).count();
+        assert!(covered > 1000);
+        assert!(framebuffer.color.iter().all(|color| color.r == 0.0 || color.r == 1.0));
+    }
+}
@@ -0,0 +1,533 @@
+use nalgebra_glm::{DVec3, Vec3, Vec4, Mat4};
+use std::f32::consts::PI;
+use std::path::Path;
+use crate::framebuffer::Framebuffer;
+use crate::shaders::Uniforms;
+use crate::planets::{Planet, PlanetType, Moon, Ring};
+use crate::gravity::{Gravity, NBody, Particle};
+use crate::orbit::Orbit;
+use crate::ship::Ship;
+use crate::scene_file::parse_scene;
+use crate::camera::Camera;
+use crate::triangle::{CullMode, FrontFace};
+use crate::render::{create_model_matrix, Renderer};
+use crate::shadows::{Occluders, RingOccluder, SphereOccluder};
+
+pub type NodeId = usize;
+
+// What a scene node draws
+pub enum Body {
+    Empty, // Grouping node, e.g. the barycenter of a binary star
+    Planet(Planet), // Stars and planets
+    Moon(Moon),
+    Ring(Ring),
+    Ship(Ship),
+}
+
+impl Body {
+    fn update(&mut self, delta_time: f32) {
+        match self {
+            Body::Planet(planet) => planet.update(delta_time),
+            Body::Moon(moon) => moon.update(delta_time),
+            _ => {}
+        }
+    }
+
+    // Orbital offset from the parent, for bodies that move on their own
+    fn orbit_position(&self) -> Option<Vec3> {
+        match self {
+            Body::Planet(planet) => Some(planet.get_current_position()),
+            Body::Moon(moon) => Some(moon.get_current_position()),
+            _ => None,
+        }
+    }
+
+    // Orbital elements and the time along them
+    fn orbit(&self) -> Option<(&Orbit, f32)> {
+        match self {
+            Body::Planet(planet) => Some((&planet.orbit, planet.orbit_time)),
+            Body::Moon(moon) => Some((&moon.orbit, moon.orbit_time)),
+            _ => None,
+        }
+    }
+
+    // Bodies with a mass take part in the N-body simulation
+    fn mass(&self) -> Option<f32> {
+        match self {
+            Body::Planet(planet) => Some(planet.mass),
+            Body::Moon(moon) => Some(moon.mass),
+            _ => None,
+        }
+    }
+
+    // Size of a sphere body; rings are sized relative to their parent's
+    fn radius(&self) -> Option<f32> {
+        match self {
+            Body::Planet(planet) => Some(planet.scale),
+            Body::Moon(moon) => Some(moon.scale),
+            _ => None,
+        }
+    }
+}
+
+// Placement of a node relative to its parent. Children inherit it, but not
+// the spin and size of the parent's own body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform { translation: Vec3::zeros(), rotation: Vec3::zeros(), scale: 1.0 }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        create_model_matrix(self.translation, self.scale, self.rotation)
+    }
+}
+
+pub struct Node {
+    pub name: String,
+    pub body: Body,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    pub transform: Transform, // Orbiting bodies overwrite its translation every update
+    world: Mat4,
+}
+
+impl Node {
+    pub fn world_matrix(&self) -> &Mat4 {
+        &self.world
+    }
+
+    pub fn world_position(&self) -> Vec3 {
+        (self.world * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz()
+    }
+}
+
+// Scene graph of the system. Nodes are stored parents first, so world
+// matrices are composed in a single pass.
+//
+// Bodies follow their Keplerian orbits unless the N-body mode is on; then
+// planets and moons move under their mutual gravity in world space, and
+// the nodes without mass (rings, ships, groups) still follow their parents.
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    physics: Option<NBody>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene::default()
+    }
+
+    // The default six-planet solar system, described in
+    // scenes/solar_system.scene
+    pub fn solar_system() -> Self {
+        parse_scene(include_str!("../scenes/solar_system.scene"), Path::new("scenes")).expect("the built-in scene is valid")
+    }
+
+    // Adds a node under `parent`, or at the root of the scene
+    pub fn add(&mut self, name: impl Into<String>, parent: Option<NodeId>, body: Body) -> NodeId {
+        let id = self.nodes.len();
+        if let Some(parent) = parent {
+            assert!(parent < id, "parent node {} doesn't exist", parent);
+            self.nodes[parent].children.push(id);
+        }
+
+        let mut transform = Transform::default();
+        if let Some(position) = body.orbit_position() {
+            transform.translation = position;
+        }
+
+        self.nodes.push(Node { name: name.into(), body, parent, children: Vec::new(), transform, world: Mat4::identity() });
+        self.update_world(id);
+        id
+    }
+
+    // Places a node and everything attached to it
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) {
+        self.nodes[id].transform = transform;
+        self.update_world_matrices();
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    // Stars and planets, in scene order
+    pub fn planets(&self) -> impl Iterator<Item = (NodeId, &Planet)> {
+        self.nodes.iter().enumerate().filter_map(|(id, node)| match &node.body {
+            Body::Planet(planet) => Some((id, planet)),
+            _ => None,
+        })
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for node in &mut self.nodes {
+            node.body.update(delta_time);
+            if let Some(position) = node.body.orbit_position() {
+                node.transform.translation = position;
+            }
+        }
+        if let Some(physics) = &mut self.physics {
+            physics.step(delta_time as f64);
+        }
+        self.update_world_matrices();
+    }
+
+    // Switches to the N-body mode, starting from where the bodies are now.
+    // Each orbit keeps its shape, but its speed is set by the gravity the
+    // body actually feels, so a scene laid out for looks starts close to
+    // bound orbits.
+    pub fn enable_physics(&mut self, gravity: Gravity) {
+        let particles = self.initial_particles(gravity);
+        self.physics = Some(NBody::new(gravity, particles));
+    }
+
+    // Back to the Keplerian orbits, at their own clocks
+    pub fn disable_physics(&mut self) {
+        self.physics = None;
+        for node in &mut self.nodes {
+            if let Some(position) = node.body.orbit_position() {
+                node.transform.translation = position;
+            }
+        }
+        self.update_world_matrices();
+    }
+
+    pub fn physics(&self) -> Option<&NBody> {
+        self.physics.as_ref()
+    }
+
+    fn initial_particles(&self, gravity: Gravity) -> Vec<Particle> {
+        let world_position = |id: NodeId| nalgebra_glm::convert::<Vec3, DVec3>(self.nodes[id].world_position());
+        let mut particles: Vec<Particle> = self.nodes.iter().enumerate()
+            .filter_map(|(id, node)| node.body.mass().map(|mass| (id, mass)))
+            .map(|(node, mass)| Particle { node, mass: mass as f64, position: world_position(node), velocity: DVec3::zeros() })
+            .collect();
+
+        // Gravity on every body where it stands
+        let forces = NBody::new(gravity, particles.clone());
+        let mut acceleration = vec![DVec3::zeros(); self.nodes.len()];
+        for (particle, a) in forces.particles().iter().zip(forces.accelerations()) {
+            acceleration[particle.node] = *a;
+        }
+
+        // Frames without a mass of their own, such as a barycenter group,
+        // accelerate with the center of mass of the bodies under them
+        let mut subtree_mass = vec![0.0; self.nodes.len()];
+        let mut subtree_force = vec![DVec3::zeros(); self.nodes.len()];
+        for particle in &particles {
+            subtree_mass[particle.node] = particle.mass;
+            subtree_force[particle.node] = acceleration[particle.node] * particle.mass;
+        }
+        for id in (0..self.nodes.len()).rev() {
+            if let Some(parent) = self.nodes[id].parent {
+                subtree_mass[parent] += subtree_mass[id];
+                let force = subtree_force[id];
+                subtree_force[parent] += force;
+            }
+            if self.nodes[id].body.mass().is_none() && subtree_mass[id] > 0.0 {
+                acceleration[id] = subtree_force[id] / subtree_mass[id];
+            }
+        }
+
+        // Velocities, parents first: the parent's, plus the orbit's at the
+        // mean motion that the pull towards the parent sustains. Root nodes
+        // orbit the fixed world origin.
+        let mut velocity = vec![DVec3::zeros(); self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate() {
+            let (parent_velocity, parent_acceleration, parent_position, parent_world) = match node.parent {
+                Some(parent) => (velocity[parent], acceleration[parent], world_position(parent), self.nodes[parent].world),
+                None => (DVec3::zeros(), DVec3::zeros(), DVec3::zeros(), Mat4::identity()),
+            };
+            velocity[id] = parent_velocity;
+
+            let Some((orbit, time)) = node.body.orbit().filter(|(orbit, _)| orbit.semi_major_axis > 0.0) else { continue };
+            let offset = world_position(id) - parent_position;
+            let pull = (parent_acceleration - acceleration[id]).dot(&offset.normalize()).max(0.0);
+            let mean_motion = (pull * offset.norm_squared() / (orbit.semi_major_axis as f64).powi(3)).sqrt();
+            let orbit = Orbit {
+                mean_anomaly: orbit.mean_anomaly + orbit.mean_motion * time,
+                mean_motion: (mean_motion as f32).copysign(orbit.mean_motion),
+                ..*orbit
+            };
+            let local = orbit.velocity(0.0);
+            let world = (parent_world * Vec4::new(local.x, local.y, local.z, 0.0)).xyz();
+            velocity[id] += nalgebra_glm::convert::<Vec3, DVec3>(world);
+        }
+
+        // Keep the barycenter at rest, so the system doesn't drift away
+        let total_mass: f64 = particles.iter().map(|particle| particle.mass).sum();
+        let drift = particles.iter().map(|particle| velocity[particle.node] * particle.mass).sum::<DVec3>() / total_mass.max(f64::MIN_POSITIVE);
+        for particle in &mut particles {
+            particle.velocity = velocity[particle.node] - drift;
+        }
+        particles
+    }
+
+    fn update_world_matrices(&mut self) {
+        for id in 0..self.nodes.len() {
+            self.update_world(id);
+        }
+    }
+
+    fn update_world(&mut self, id: NodeId) {
+        let parent_world = match self.nodes[id].parent {
+            Some(parent) => self.nodes[parent].world,
+            None => Mat4::identity(),
+        };
+
+        // Simulated bodies are placed in world space; their translation is
+        // kept as the offset from the parent
+        let simulated = self.physics.as_ref().and_then(|physics| physics.particle(id));
+        let node = &mut self.nodes[id];
+        if let Some(particle) = simulated {
+            let position: Vec3 = nalgebra_glm::convert(particle.position);
+            node.transform.translation = position - (parent_world * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+            node.world = create_model_matrix(position, node.transform.scale, node.transform.rotation);
+            return;
+        }
+        node.world = parent_world * node.transform.matrix();
+    }
+
+    // Matrix of a node's own body: its world placement plus its spin and size
+    fn model_matrix(&self, node: &Node, time: f32) -> Mat4 {
+        let body_matrix = match &node.body {
+            Body::Empty => return node.world,
+            Body::Planet(planet) => create_model_matrix(Vec3::zeros(), planet.scale, Vec3::new(0.0, planet.current_rotation, 0.0)),
+            Body::Moon(moon) => create_model_matrix(Vec3::zeros(), moon.scale, Vec3::zeros()),
+            Body::Ring(_) => {
+                let parent_radius = node.parent.and_then(|parent| self.nodes[parent].body.radius()).unwrap_or(1.0);
+                ring_model_matrix(ring_scale(parent_radius), time)
+            }
+            Body::Ship(ship) => create_model_matrix(Vec3::zeros(), ship.scale, ship.rotation),
+        };
+        node.world * body_matrix
+    }
+
+    pub fn render<'a>(&'a self, framebuffer: &mut Framebuffer, renderer: &mut Renderer<'a>, frame_uniforms: &Uniforms, time: f32) {
+        // The first star is the light source of the system; everything else casts shadows
+        let mut frame_uniforms = *frame_uniforms;
+        if let Some((id, star)) = self.planets().find(|(_, planet)| planet.planet_type == PlanetType::Star) {
+            frame_uniforms.light.position = self.nodes[id].world_position();
+            frame_uniforms.light.radius = star.scale;
+        }
+        frame_uniforms.occluders = self.occluders(time);
+
+        for node in &self.nodes {
+            // Rings are double-sided, visible from above and below; the
+            // default ring shaders blend, so they land in the transparent pass.
+            // Ships keep the counter-clockwise winding of their OBJ files.
+            let (mesh, shader, cull_mode, front_face) = match &node.body {
+                Body::Empty => continue,
+                Body::Planet(planet) => (planet.sphere.get_mesh(), planet.shader.as_ref(), CullMode::Back, FrontFace::Clockwise),
+                Body::Moon(moon) => (moon.sphere.get_mesh(), moon.shader.as_ref(), CullMode::Back, FrontFace::Clockwise),
+                Body::Ring(ring) => (&ring.mesh, ring.shader.as_ref(), CullMode::None, FrontFace::Clockwise),
+                Body::Ship(ship) => (&ship.mesh, ship.shader.as_ref(), CullMode::Back, FrontFace::CounterClockwise),
+            };
+
+            let uniforms = Uniforms { model_matrix: self.model_matrix(node, time), ..frame_uniforms };
+            renderer.draw(framebuffer, &uniforms, mesh, shader, time, cull_mode, front_face);
+        }
+
+        renderer.finish(framebuffer);
+    }
+
+    // Planets, moons and rings as seen by the star's light at `time`
+    fn occluders(&self, time: f32) -> Occluders {
+        let mut occluders = Occluders::default();
+
+        for node in &self.nodes {
+            let center = node.world_position();
+            match &node.body {
+                Body::Planet(planet) if planet.planet_type != PlanetType::Star => {
+                    occluders.push_sphere(SphereOccluder { center, radius: planet.scale });
+                }
+                Body::Moon(moon) => {
+                    occluders.push_sphere(SphereOccluder { center, radius: moon.scale });
+                }
+                Body::Ring(ring) => {
+                    let model_matrix = self.model_matrix(node, time);
+                    let normal = (model_matrix * Vec4::new(0.0, 1.0, 0.0, 0.0)).xyz().normalize();
+                    let ring_scale = (model_matrix * Vec4::new(1.0, 0.0, 0.0, 0.0)).xyz().norm();
+
+                    occluders.push_ring(RingOccluder {
+                        center,
+                        normal,
+                        inner_radius: ring.inner_radius * ring_scale,
+                        outer_radius: ring.outer_radius * ring_scale,
+                        style: ring.style,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        occluders
+    }
+}
+
+// Camera framing the whole default system
+pub fn default_camera() -> Camera {
+    Camera::new(
+        Vec3::new(0.0, 300.0, 800.0), // Above and in front of the system
+        Vec3::new(0.0, 0.0, 0.0),     // Looking at the sun
+        Vec3::new(0.0, 1.0, 0.0),
+        PI / 4.0,                     // 45° vertical field of view
+        1.0,
+        5000.0,
+    )
+}
+
+// Planet radii per ring mesh unit
+pub const RING_SCALE: f32 = 0.012;
+
+// Ring meshes are built in their own units; this maps them around a planet
+fn ring_scale(planet_scale: f32) -> f32 {
+    planet_scale * RING_SCALE
+}
+
+fn ring_model_matrix(scale: f32, time: f32) -> Mat4 {
+    let rotation = Vec3::new(75.0_f32.to_radians(), time * 0.2, 0.0); // Slight tilt and slow rotation
+    create_model_matrix(Vec3::zeros(), scale, rotation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).norm() < 1e-3, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn moons_of_moons_follow_their_parents() {
+        let mut scene = Scene::new();
+        let star = scene.add("Star", None, Body::Planet(Planet::new(PlanetType::Star, Vec3::zeros(), 10.0, 0.0, 0.0, 0.0)));
+        let planet = scene.add("Planet", Some(star), Body::Planet(Planet::new(PlanetType::RockyPlanet, Vec3::zeros(), 5.0, 1.0, 0.5, 100.0)));
+        let moon = scene.add("Moon", Some(planet), Body::Moon(Moon::new(20.0, 2.0, 1.0)));
+        let submoon = scene.add("Submoon", Some(moon), Body::Moon(Moon::new(3.0, -1.0, 0.2)));
+
+        // Bodies start on their orbits, not at their parent's center
+        assert_near(scene.node(submoon).world_position(), Vec3::new(123.0, 0.0, 0.0));
+
+        scene.update(1.0);
+        let planet_position = Vec3::new(100.0 * 0.5f32.cos(), 0.0, 100.0 * 0.5f32.sin());
+        let moon_position = planet_position + Vec3::new(20.0 * 2.0f32.cos(), 0.0, 20.0 * 2.0f32.sin());
+        assert_near(scene.node(moon).world_position(), moon_position);
+        assert_near(scene.node(submoon).world_position(), moon_position + Vec3::new(3.0 * 1.0f32.cos(), 0.0, -3.0 * 1.0f32.sin()));
+    }
+
+    #[test]
+    fn children_inherit_placement_but_not_the_parent_body() {
+        let mut scene = Scene::new();
+        let group = scene.add("Tilted", None, Body::Empty);
+        let planet = scene.add("Planet", Some(group), Body::Planet(Planet::new(PlanetType::IcePlanet, Vec3::new(10.0, 0.0, 0.0), 4.0, 3.0, 0.0, 0.0)));
+        let moon = scene.add("Moon", Some(planet), Body::Moon(Moon::new(5.0, 0.0, 1.0)));
+        scene.set_transform(group, Transform {
+            translation: Vec3::new(0.0, 50.0, 0.0),
+            rotation: Vec3::new(0.0, 0.0, std::f32::consts::FRAC_PI_2),
+            scale: 1.0,
+        });
+        scene.update(1.0);
+
+        // The group's quarter turn carries the whole branch with it, while
+        // the planet's spin and radius stay on its own sphere
+        assert_near(scene.node(planet).world_position(), Vec3::new(0.0, 60.0, 0.0));
+        assert_near(scene.node(moon).world_position(), Vec3::new(0.0, 65.0, 0.0));
+        let planet_model = scene.model_matrix(scene.node(planet), 0.0);
+        assert!(((planet_model * Vec4::new(1.0, 0.0, 0.0, 0.0)).xyz().norm() - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn physics_mode_keeps_a_circular_orbit_and_carries_children_along() {
+        let mut scene = Scene::new();
+        let star = scene.add("Star", None, Body::Planet(Planet::new(PlanetType::Star, Vec3::zeros(), 10.0, 0.0, 0.0, 0.0)));
+        let planet = scene.add("Planet", None, Body::Planet(Planet::new(PlanetType::RockyPlanet, Vec3::zeros(), 5.0, 0.0, 1.0, 100.0)));
+        let ring = scene.add("Ring", Some(planet), Body::Ring(Ring::new(1.0, 2.0, 8)));
+        scene.enable_physics(Gravity::default());
+
+        // The kinematic speed is ignored: the period is set by the star's mass
+        let star_mass = 8.0 * 10.0f32.powi(3);
+        let period = 2.0 * PI * (100.0f32.powi(3) / star_mass).sqrt();
+        let start = scene.node(planet).world_position();
+        for _ in 0..100 {
+            scene.update(period / 200.0);
+        }
+        assert!((scene.node(planet).world_position() + start).norm() < 1.0);
+        for _ in 0..100 {
+            scene.update(period / 200.0);
+        }
+        assert!((scene.node(planet).world_position() - start).norm() < 1.0);
+        assert_near(scene.node(ring).world_position(), scene.node(planet).world_position());
+
+        // The star wobbles about the barycenter instead of sitting still
+        assert!(scene.node(star).world_position().norm() > 0.0);
+        assert!(scene.physics().unwrap().diagnostics().energy_drift.abs() < 1e-6);
+    }
+
+    #[test]
+    fn physics_mode_starts_moons_bound_to_their_planet() {
+        let mut scene = Scene::new();
+        scene.add("Star", None, Body::Planet(Planet::new(PlanetType::Star, Vec3::zeros(), 10.0, 0.0, 0.0, 0.0)));
+        let planet = scene.add("Planet", None, Body::Planet(
+            Planet::new(PlanetType::GasGiant, Vec3::zeros(), 8.0, 0.0, 1.0, 1000.0).with_mass(4000.0),
+        ));
+        let moon = scene.add("Moon", Some(planet), Body::Moon(Moon::new(20.0, 1.0, 1.0)));
+        scene.enable_physics(Gravity::default());
+
+        for _ in 0..120 {
+            scene.update(0.25);
+            let distance = (scene.node(moon).world_position() - scene.node(planet).world_position()).norm();
+            assert!((distance - 20.0).abs() < 1.0, "moon drifted to {}", distance);
+        }
+
+        // Back to the Keplerian orbit at its own clock
+        scene.disable_physics();
+        assert_near(scene.node(moon).transform.translation, Vec3::new(20.0 * 30.0f32.cos(), 0.0, 20.0 * 30.0f32.sin()));
+    }
+    #[test]
+    fn ships_loaded_from_obj_files_are_not_culled() {
+        use crate::render::{create_frame_uniforms, RasterMode};
+
+        // One triangle, counter-clockwise as OBJ files wind it, facing +z.
+        // The loader flips y and z, so it faces -z in the scene.
+        let path = std::env::temp_dir().join(format!("spaceship_ship_{}.obj", std::process::id()));
+        std::fs::write(&path, "v -1 -1 0\nv 1 -1 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let ship = Ship::load(&path.to_string_lossy(), 10.0);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut scene = Scene::new();
+        scene.add("Ship", None, Body::Ship(ship.unwrap()));
+
+        let covered = |eye: Vec3| {
+            let camera = Camera::new(eye, Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), PI / 3.0, 1.0, 100.0);
+            let mut framebuffer = Framebuffer::new(64, 64);
+            framebuffer.clear();
+            let frame_uniforms = create_frame_uniforms(&camera, 64, 64);
+            scene.render(&mut framebuffer, &mut Renderer::new(RasterMode::Streaming), &frame_uniforms, 0.0);
+            framebuffer.zbuffer.iter().filter(|depth| depth.is_finite()).count()
+        };
+
+        // Seen from the front it covers pixels; from behind it is culled
+        assert!(covered(Vec3::new(0.0, 0.0, -30.0)) > 100);
+        assert_eq!(covered(Vec3::new(0.0, 0.0, 30.0)), 0);
+    }
+
+}
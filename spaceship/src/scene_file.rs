@@ -0,0 +1,552 @@
+use std::fmt;
+use std::path::Path;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::lighting::Lit;
+use crate::orbit::Orbit;
+use crate::planets::{moon_shader, Moon, Planet, PlanetType, Ring, RingStyle};
+use crate::scene::{Body, NodeId, Scene, Transform, RING_SCALE};
+use crate::ship::Ship;
+use crate::shaders::FragmentShader;
+
+// Scene description files: a list of [planet], [moon], [ring], [group] and
+// [ship] sections of `key = value` lines, each adding a node to the scene
+// graph. See scenes/solar_system.scene for the format.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub line: Option<usize>, // None when the error is about the file as a whole
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, SceneError> {
+    Err(SceneError { line: Some(line), message: message.into() })
+}
+
+fn file_error<T>(message: impl Into<String>) -> Result<T, SceneError> {
+    Err(SceneError { line: None, message: message.into() })
+}
+
+// Reads and validates a scene file; errors name the file and line. Model
+// paths are relative to the file.
+pub fn load_scene(path: &Path) -> Result<Scene, String> {
+    let source = std::fs::read_to_string(path).map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    parse_scene(&source, base_dir).map_err(|error| match error.line {
+        Some(line) => format!("{}:{}: {}", path.display(), line, error.message),
+        None => format!("{}: {}", path.display(), error.message),
+    })
+}
+
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let mut scene = Scene::new();
+    let mut has_star = false;
+
+    for section in sections(source)? {
+        // Every body can be named and attached to a node declared above it
+        let name = match section.get("name") {
+            Some(field) if scene.find(field.value).is_some() => {
+                return error(field.line, format!("a node named `{}` already exists", field.value));
+            }
+            Some(field) => field.value,
+            None => "",
+        };
+        let parent = match section.get("parent") {
+            Some(_) => Some(section.node("parent", &scene)?),
+            None => None,
+        };
+
+        match section.kind {
+            "planet" => {
+                section.field("name")?;
+                let planet_type = section.planet_type("type")?;
+                has_star |= planet_type == PlanetType::Star;
+
+                let mut planet = Planet::new(
+                    planet_type,
+                    Vec3::zeros(),
+                    section.positive("radius")?,
+                    section.number("rotation_speed", 0.0)?,
+                    0.0,
+                    0.0,
+                )
+                .with_orbit(section.orbit(section.non_negative("orbit_radius", 0.0)?)?);
+                if section.get("shader").is_some() {
+                    planet = planet.with_shader(section.shader("shader")?);
+                }
+                if section.get("mass").is_some() {
+                    planet = planet.with_mass(section.positive("mass")?);
+                }
+
+                section.allow(&[&["name", "parent", "type", "shader", "radius", "rotation_speed", "mass"], ORBIT_KEYS].concat())?;
+                scene.add(name, parent, Body::Planet(planet));
+            }
+            "moon" => {
+                let parent = section.node("parent", &scene)?;
+                let mut moon = Moon::new(0.0, 0.0, section.positive("radius")?)
+                    .with_orbit(section.orbit(section.positive("orbit_radius")?)?);
+                if section.get("shader").is_some() {
+                    moon = moon.with_shader(section.shader("shader")?);
+                }
+                if section.get("mass").is_some() {
+                    moon = moon.with_mass(section.positive("mass")?);
+                }
+
+                section.allow(&[&["name", "parent", "shader", "radius", "mass"], ORBIT_KEYS].concat())?;
+                scene.add(name, Some(parent), Body::Moon(moon));
+            }
+            "ring" => {
+                let parent = section.node("parent", &scene)?;
+                let inner_radius = section.positive("inner_radius")?;
+                let outer_radius = section.positive("outer_radius")?;
+                if outer_radius <= inner_radius {
+                    return error(section.field("outer_radius")?.line, "outer_radius must be larger than inner_radius");
+                }
+
+                let segments = section.segments("segments", 96)?;
+                let defaults = RingStyle::default();
+                let style = RingStyle::new(
+                    section.color("color", defaults.color)?,
+                    section.non_negative("bands", defaults.bands)?,
+                    section.fraction("opacity", defaults.opacity)?,
+                );
+
+                section.allow(&["name", "parent", "inner_radius", "outer_radius", "segments", "color", "bands", "opacity"])?;
+                let ring = Ring::new(inner_radius / RING_SCALE, outer_radius / RING_SCALE, segments);
+                scene.add(name, Some(parent), Body::Ring(ring.with_style(style)));
+            }
+            "group" => {
+                section.field("name")?;
+                let transform = Transform { translation: section.vector("position")?, ..Transform::default() };
+
+                section.allow(&["name", "parent", "position"])?;
+                let id = scene.add(name, parent, Body::Empty);
+                scene.set_transform(id, transform);
+            }
+            "ship" => {
+                let model = section.field("model")?;
+                let path = base_dir.join(model.value);
+                let ship = match Ship::load(&path.to_string_lossy(), section.positive("scale")?) {
+                    Ok(ship) => ship,
+                    Err(load_error) => return error(model.line, format!("cannot load model {}: {}", path.display(), load_error)),
+                };
+                let rotation = section.vector("rotation")?;
+                let transform = Transform {
+                    translation: section.vector("position")?,
+                    rotation: Vec3::new(rotation.x.to_radians(), rotation.y.to_radians(), rotation.z.to_radians()),
+                    ..Transform::default()
+                };
+
+                section.allow(&["name", "parent", "model", "scale", "position", "rotation"])?;
+                let id = scene.add(name, parent, Body::Ship(ship));
+                scene.set_transform(id, transform);
+            }
+            other => {
+                return error(section.line, format!("unknown section [{}], expected [planet], [moon], [ring], [group] or [ship]", other));
+            }
+        }
+    }
+
+    if !has_star {
+        return file_error("the scene needs a planet with `type = star` to light it");
+    }
+
+    Ok(scene)
+}
+
+// Keplerian elements of planets and moons. The orbit radius is the
+// semi-major axis and the speed the mean motion; angles are in degrees.
+const ORBIT_KEYS: &[&str] = &[
+    "orbit_radius",
+    "orbit_speed",
+    "eccentricity",
+    "inclination",
+    "ascending_node",
+    "periapsis",
+    "mean_anomaly",
+];
+
+// Shader names accepted by the `shader` key of planets and moons
+pub const SHADER_NAMES: [&str; 7] = ["star", "rocky", "gas_giant", "ice", "volcanic", "ringed", "moon"];
+
+fn planet_type(name: &str) -> Option<PlanetType> {
+    match name {
+        "star" => Some(PlanetType::Star),
+        "rocky" => Some(PlanetType::RockyPlanet),
+        "gas_giant" => Some(PlanetType::GasGiant),
+        "ice" => Some(PlanetType::IcePlanet),
+        "volcanic" => Some(PlanetType::VolcanicPlanet),
+        "ringed" => Some(PlanetType::RingedPlanet),
+        _ => None,
+    }
+}
+
+fn builtin_shader(name: &str) -> Option<Box<dyn FragmentShader>> {
+    match name {
+        "moon" => Some(Box::new(Lit(moon_shader))),
+        _ => planet_type(name).map(|planet_type| planet_type.default_shader()),
+    }
+}
+
+struct Field<'a> {
+    key: &'a str,
+    value: &'a str,
+    line: usize,
+}
+
+struct Section<'a> {
+    kind: &'a str,
+    line: usize,
+    fields: Vec<Field<'a>>,
+}
+
+fn sections(source: &str) -> Result<Vec<Section<'_>>, SceneError> {
+    let mut sections: Vec<Section> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let (number, line) = (index + 1, line.trim());
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(kind) = line.strip_prefix('[') {
+            let Some(kind) = kind.strip_suffix(']') else {
+                return error(number, format!("unclosed section header `{}`", line));
+            };
+            sections.push(Section { kind: kind.trim(), line: number, fields: Vec::new() });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return error(number, format!("expected `key = value`, got `{}`", line));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let Some(section) = sections.last_mut() else {
+            return error(number, format!("`{}` is outside of any section", key));
+        };
+        if key.is_empty() || value.is_empty() {
+            return error(number, format!("expected `key = value`, got `{}`", line));
+        }
+        if let Some(previous) = section.fields.iter().find(|field| field.key == key) {
+            return error(number, format!("`{}` is already set on line {}", key, previous.line));
+        }
+        section.fields.push(Field { key, value, line: number });
+    }
+
+    Ok(sections)
+}
+
+impl<'a> Section<'a> {
+    fn get(&self, key: &str) -> Option<&Field<'a>> {
+        self.fields.iter().find(|field| field.key == key)
+    }
+
+    fn field(&self, key: &str) -> Result<&Field<'a>, SceneError> {
+        match self.get(key) {
+            Some(field) => Ok(field),
+            None => error(self.line, format!("[{}] is missing `{}`", self.kind, key)),
+        }
+    }
+
+    // Rejects keys that the section kind doesn't use, which are most
+    // likely typos
+    fn allow(&self, keys: &[&str]) -> Result<(), SceneError> {
+        match self.fields.iter().find(|field| !keys.contains(&field.key)) {
+            Some(field) => error(
+                field.line,
+                format!("unknown key `{}` in [{}], expected one of: {}", field.key, self.kind, keys.join(", ")),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_number(field: &Field) -> Result<f32, SceneError> {
+        match field.value.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => error(field.line, format!("`{}` expects a number, got `{}`", field.key, field.value)),
+        }
+    }
+
+    // Optional number, `default` when the key is absent
+    fn number(&self, key: &str, default: f32) -> Result<f32, SceneError> {
+        self.get(key).map_or(Ok(default), Self::parse_number)
+    }
+
+    // Required number above zero
+    fn positive(&self, key: &str) -> Result<f32, SceneError> {
+        let field = self.field(key)?;
+        match Self::parse_number(field)? {
+            value if value > 0.0 => Ok(value),
+            _ => error(field.line, format!("`{}` must be greater than 0", key)),
+        }
+    }
+
+    fn non_negative(&self, key: &str, default: f32) -> Result<f32, SceneError> {
+        match self.number(key, default)? {
+            value if value >= 0.0 => Ok(value),
+            _ => error(self.field(key)?.line, format!("`{}` can't be negative", key)),
+        }
+    }
+
+    fn fraction(&self, key: &str, default: f32) -> Result<f32, SceneError> {
+        match self.number(key, default)? {
+            value if (0.0..=1.0).contains(&value) => Ok(value),
+            _ => error(self.field(key)?.line, format!("`{}` must be between 0 and 1", key)),
+        }
+    }
+
+    fn segments(&self, key: &str, default: u32) -> Result<u32, SceneError> {
+        let Some(field) = self.get(key) else {
+            return Ok(default);
+        };
+        match field.value.parse::<u32>() {
+            Ok(segments) if segments >= 3 => Ok(segments),
+            _ => error(field.line, format!("`{}` expects a whole number of at least 3", key)),
+        }
+    }
+
+    // `#rrggbb`
+    fn color(&self, key: &str, default: Color) -> Result<Color, SceneError> {
+        let Some(field) = self.get(key) else {
+            return Ok(default);
+        };
+        let hex = field.value.strip_prefix('#').filter(|hex| hex.len() == 6);
+        match hex.and_then(|hex| u32::from_str_radix(hex, 16).ok()) {
+            Some(hex) => Ok(Color::from_hex(hex)),
+            None => error(field.line, format!("`{}` expects a color like #ffaa00, got `{}`", key, field.value)),
+        }
+    }
+
+    fn planet_type(&self, key: &str) -> Result<PlanetType, SceneError> {
+        let field = self.field(key)?;
+        match planet_type(field.value) {
+            Some(planet_type) => Ok(planet_type),
+            None => error(
+                field.line,
+                format!("unknown planet type `{}`, expected one of: {}", field.value, SHADER_NAMES[..6].join(", ")),
+            ),
+        }
+    }
+
+    fn shader(&self, key: &str) -> Result<Box<dyn FragmentShader>, SceneError> {
+        let field = self.field(key)?;
+        match builtin_shader(field.value) {
+            Some(shader) => Ok(shader),
+            None => error(field.line, format!("unknown shader `{}`, expected one of: {}", field.value, SHADER_NAMES.join(", "))),
+        }
+    }
+
+    fn orbit(&self, semi_major_axis: f32) -> Result<Orbit, SceneError> {
+        let eccentricity = self.number("eccentricity", 0.0)?;
+        if !(0.0..1.0).contains(&eccentricity) {
+            return error(self.field("eccentricity")?.line, "`eccentricity` must be at least 0 and below 1");
+        }
+
+        Ok(Orbit {
+            semi_major_axis,
+            eccentricity,
+            inclination: self.number("inclination", 0.0)?.to_radians(),
+            ascending_node: self.number("ascending_node", 0.0)?.to_radians(),
+            periapsis: self.number("periapsis", 0.0)?.to_radians(),
+            mean_anomaly: self.number("mean_anomaly", 0.0)?.to_radians(),
+            mean_motion: self.number("orbit_speed", 0.0)?,
+        })
+    }
+
+    // A node named earlier in the file
+    fn node(&self, key: &str, scene: &Scene) -> Result<NodeId, SceneError> {
+        let field = self.field(key)?;
+        match scene.find(field.value) {
+            Some(id) => Ok(id),
+            None => error(field.line, format!("no node named `{}` is declared above", field.value)),
+        }
+    }
+
+    // Optional `x y z`, zero when absent
+    fn vector(&self, key: &str) -> Result<Vec3, SceneError> {
+        let Some(field) = self.get(key) else {
+            return Ok(Vec3::zeros());
+        };
+        let components: Result<Vec<f32>, _> = field.value.split_whitespace().map(str::parse).collect();
+        match components.as_deref() {
+            Ok(&[x, y, z]) if x.is_finite() && y.is_finite() && z.is_finite() => Ok(Vec3::new(x, y, z)),
+            _ => error(field.line, format!("`{}` expects three numbers like `10 0 -5`, got `{}`", key, field.value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINARY: &str = "
+[group]
+name = Barycenter
+position = 0 10 0
+
+[planet]
+name = Castor
+parent = Barycenter
+type = star
+radius = 20
+orbit_radius = 30
+orbit_speed = 0.2
+
+[planet]
+name = Pollux
+parent = Barycenter
+type = star
+shader = volcanic
+radius = 15
+orbit_radius = 40
+orbit_speed = 0.2
+mean_anomaly = 180
+
+[planet]
+name = Rock
+parent = Barycenter
+type = rocky
+radius = 10
+orbit_radius = 200
+orbit_speed = 0.5
+eccentricity = 0.5
+inclination = 10
+
+[moon]
+name = Pebble
+parent = Rock
+radius = 2
+orbit_radius = 20
+
+[moon]
+parent = Pebble
+radius = 0.5
+orbit_radius = 4
+
+[ring]
+parent = Rock
+inner_radius = 1.2
+outer_radius = 1.8
+color = #ff8000
+";
+
+    fn parse(source: &str) -> Result<Scene, SceneError> {
+        parse_scene(source, Path::new("scenes"))
+    }
+
+    #[test]
+    fn scene_file_builds_the_node_hierarchy() {
+        let scene = parse(BINARY).unwrap();
+        let rock = scene.find("Rock").unwrap();
+        let pebble = scene.find("Pebble").unwrap();
+
+        assert_eq!(scene.nodes().len(), 7);
+        assert_eq!(scene.node(rock).parent, scene.find("Barycenter"));
+        assert_eq!(scene.node(pebble).parent, Some(rock));
+        assert_eq!(scene.node(6).parent, Some(rock));
+        assert_eq!(scene.node(5).parent, Some(pebble));
+
+        // Both stars circle the barycenter from opposite sides
+        assert!((scene.node(scene.find("Castor").unwrap()).world_position() - Vec3::new(30.0, 10.0, 0.0)).norm() < 1e-3);
+        assert!((scene.node(scene.find("Pollux").unwrap()).world_position() - Vec3::new(-40.0, 10.0, 0.0)).norm() < 1e-3);
+        // Rock starts at the periapsis of its elliptical orbit
+        assert!((scene.node(5).world_position() - Vec3::new(124.0, 10.0, 0.0)).norm() < 1e-3);
+        let Body::Planet(rock_planet) = &scene.node(rock).body else {
+            panic!("expected a planet");
+        };
+        assert_eq!(rock_planet.orbit.eccentricity, 0.5);
+        assert!((rock_planet.orbit.inclination - 10f32.to_radians()).abs() < 1e-6);
+
+        let Body::Ring(ring) = &scene.node(6).body else {
+            panic!("expected a ring");
+        };
+        assert!((ring.outer_radius * RING_SCALE - 1.8).abs() < 1e-5);
+        assert_eq!(ring.style.color, Color::new(255, 128, 0));
+        assert_eq!(ring.style.opacity, RingStyle::default().opacity);
+    }
+
+    #[test]
+    fn default_scene_file_matches_the_built_in_system() {
+        let scene = Scene::solar_system();
+        let types: Vec<PlanetType> = scene.planets().map(|(_, planet)| planet.planet_type).collect();
+        assert_eq!(types, [
+            PlanetType::Star,
+            PlanetType::RockyPlanet,
+            PlanetType::GasGiant,
+            PlanetType::IcePlanet,
+            PlanetType::VolcanicPlanet,
+            PlanetType::RingedPlanet,
+        ]);
+
+        let parent_name = |body: fn(&Body) -> bool| -> Vec<&str> {
+            scene.nodes().iter().filter(|node| body(&node.body)).map(|node| scene.node(node.parent.unwrap()).name.as_str()).collect()
+        };
+        assert_eq!(parent_name(|body| matches!(body, Body::Moon(_))), ["Terra"]);
+        assert_eq!(parent_name(|body| matches!(body, Body::Ring(_))), ["Jove", "Jove", "Jove", "Annulus", "Annulus", "Annulus"]);
+    }
+
+    #[test]
+    fn bundled_scenes_load() {
+        for entry in std::fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            assert!(load_scene(&path).is_ok(), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn ships_load_their_model_relative_to_the_scene_file() {
+        let source = "[planet]\nname = Sun\ntype = star\nradius = 50\n\n[ship]\nname = Explorer\nmodel = ../assets/Spaceship.obj\nscale = 2\nposition = 0 0 100\n";
+        let scene = parse(source).unwrap();
+        let ship = scene.find("Explorer").unwrap();
+
+        assert!(matches!(scene.node(ship).body, Body::Ship(_)));
+        assert_eq!(scene.node(ship).world_position(), Vec3::new(0.0, 0.0, 100.0));
+
+        let missing = parse(&source.replace("Spaceship.obj", "Missing.obj")).err().unwrap();
+        assert_eq!(missing.line, Some(8));
+        assert!(missing.message.starts_with("cannot load model scenes/../assets/Missing.obj"), "{}", missing.message);
+    }
+
+    #[test]
+    fn invalid_scenes_report_the_line() {
+        let star = "[planet]\nname = Sun\ntype = star\nradius = 50\n";
+        for (source, line, message) in [
+            ("radius = 3\n", 1, "`radius` is outside of any section"),
+            ("[comet]\n", 1, "unknown section [comet], expected [planet], [moon], [ring], [group] or [ship]"),
+            ("[planet\n", 1, "unclosed section header `[planet`"),
+            (&format!("{star}[planet]\nname = Rock\ntype = rocky\n"), 5, "[planet] is missing `radius`"),
+            (&format!("{star}[planet]\ntype = rocky\nradius = 5\n"), 5, "[planet] is missing `name`"),
+            (&format!("{star}[planet]\nname = Sun\n"), 6, "a node named `Sun` already exists"),
+            (&format!("{star}[planet]\nname = Rock\ntype = lava\n"), 7, "unknown planet type `lava`, expected one of: star, rocky, gas_giant, ice, volcanic, ringed"),
+            (&format!("{star}radius = 4\n"), 5, "`radius` is already set on line 4"),
+            (&format!("{star}colour = #ffffff\n"), 5, "unknown key `colour` in [planet], expected one of: name, parent, type, shader, radius, rotation_speed, mass, orbit_radius, orbit_speed, eccentricity, inclination, ascending_node, periapsis, mean_anomaly"),
+            (&format!("{star}[moon]\nparent = Sun\nradius = 2\norbit_radius = 10\neccentricity = 1\n"), 9, "`eccentricity` must be at least 0 and below 1"),
+            (&format!("{star}[moon]\nparent = Rock\n"), 6, "no node named `Rock` is declared above"),
+            (&format!("{star}[moon]\nradius = 2\n"), 5, "[moon] is missing `parent`"),
+            (&format!("{star}[moon]\nparent = Sun\nradius = -2\norbit_radius = 10\n"), 7, "`radius` must be greater than 0"),
+            (&format!("{star}[ring]\nparent = Sun\ninner_radius = 2\nouter_radius = 1\n"), 8, "outer_radius must be larger than inner_radius"),
+            (&format!("{star}[ring]\nparent = Sun\ninner_radius = 1\nouter_radius = 2\nopacity = 1.5\n"), 9, "`opacity` must be between 0 and 1"),
+            (&format!("{star}[ring]\nparent = Sun\ninner_radius = 1\nouter_radius = 2\ncolor = orange\n"), 9, "`color` expects a color like #ffaa00, got `orange`"),
+            (&format!("{star}[group]\nname = Center\nposition = 1 2\n"), 7, "`position` expects three numbers like `10 0 -5`, got `1 2`"),
+        ] {
+            let error = parse(source).err().unwrap_or_else(|| panic!("accepted {:?}", source));
+            assert_eq!(error, SceneError { line: Some(line), message: message.to_string() }, "{:?}", source);
+        }
+
+        // Not a problem with any one line
+        let error = parse("[planet]\nname = Rock\ntype = rocky\nradius = 5\n# no star\n").err().unwrap();
+        assert_eq!(error.line, None);
+        assert_eq!(error.to_string(), "the scene needs a planet with `type = star` to light it");
+    }
+}
@@ -1,17 +1,32 @@
-use nalgebra_glm::{Vec3, Mat4};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4};
 use crate::vertex::Vertex;
+use crate::color::{Color, LinearColor};
+use crate::framebuffer::BlendMode;
+use crate::lighting::{illuminate, PointLight};
+use crate::shadows::Occluders;
 
+#[derive(Clone, Copy)]
 pub struct Uniforms {
     pub model_matrix: Mat4,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4,
+    pub camera_position: Vec3, // World space, for specular highlights
+    pub light: PointLight,
+    pub occluders: Occluders,  // Shadow casters for `light`
 }
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-    let position = Vec3::new(vertex.position.x, vertex.position.y, vertex.position.z);
-    
-    // Transform position
-    let transformed_position = uniforms.model_matrix * Vec3::new(position.x, position.y, position.z).push(1.0);
-    let transformed_pos = Vec3::new(transformed_position.x, transformed_position.y, transformed_position.z);
-    
+    let position = Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+
+    // Model -> world -> view -> clip space
+    let clip_position = uniforms.projection_matrix
+        * uniforms.view_matrix
+        * uniforms.model_matrix
+        * position;
+
+    let world_position = uniforms.model_matrix * position;
+
     // Transform normal (assuming no non-uniform scaling)
     let transformed_normal = uniforms.model_matrix * vertex.normal.push(0.0);
     let transformed_norm = Vec3::new(transformed_normal.x, transformed_normal.y, transformed_normal.z);
@@ -21,7 +36,81 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         normal: vertex.normal,
         tex_coords: vertex.tex_coords,
         color: vertex.color,
-        transformed_position: transformed_pos,
+        world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+        clip_position,
+        // Filled in by viewport_transform once the primitive has been clipped
+        transformed_position: Vec3::new(clip_position.x, clip_position.y, clip_position.z),
         transformed_normal: transformed_norm,
     }
 }
+
+pub fn viewport_transform(vertex: &mut Vertex, uniforms: &Uniforms) {
+    // Perspective division to normalized device coordinates
+    let w = vertex.clip_position.w;
+    let ndc_position = Vec4::new(
+        vertex.clip_position.x / w,
+        vertex.clip_position.y / w,
+        vertex.clip_position.z / w,
+        1.0,
+    );
+
+    // NDC -> screen space (z keeps the depth used by the z-buffer)
+    let screen_position = uniforms.viewport_matrix * ndc_position;
+    vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+}
+
+// Interpolated attributes handed to a fragment shader
+pub struct FragmentInput<'a> {
+    pub position: Vec3,       // Object space, for procedural patterns
+    pub world_position: Vec3,
+    pub normal: Vec3,         // World space, normalized
+    pub uv: Vec2,
+    pub time: f32,
+    pub uniforms: &'a Uniforms,
+}
+
+// Per-pixel shading into the linear HDR target. Shared by the tiled
+// rasterizer's worker threads, hence Send + Sync.
+pub trait FragmentShader: Send + Sync {
+    fn shade(&self, input: &FragmentInput) -> LinearColor;
+
+    // Anything but `Opaque` is drawn in the renderer's transparent pass
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Opaque
+    }
+}
+
+// Plain functions and closures can be used as shaders directly, returning
+// either a `LinearColor` or an 8-bit sRGB `Color`
+impl<F, C> FragmentShader for F
+where
+    F: Fn(&FragmentInput) -> C + Send + Sync,
+    C: Into<LinearColor>,
+{
+    fn shade(&self, input: &FragmentInput) -> LinearColor {
+        self(input).into()
+    }
+}
+
+// Draws the wrapped shader with another blend mode, using the alpha of
+// its colors as coverage
+pub struct Blended<S>(pub S, pub BlendMode);
+
+impl<S: FragmentShader> FragmentShader for Blended<S> {
+    fn shade(&self, input: &FragmentInput) -> LinearColor {
+        self.0.shade(input)
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.1
+    }
+}
+
+// Default lighting for spaceship
+pub struct SpaceshipShader;
+
+impl FragmentShader for SpaceshipShader {
+    fn shade(&self, input: &FragmentInput) -> LinearColor {
+        illuminate(Color::new(100, 150, 255).into(), input)
+    }
+}
@@ -0,0 +1,223 @@
+use nalgebra_glm::{Vec3, dot};
+use crate::planets::RingStyle;
+
+// Capacity of the occluder lists carried in `Uniforms`
+pub const MAX_SPHERE_OCCLUDERS: usize = 32;
+pub const MAX_RING_OCCLUDERS: usize = 16;
+
+// A planet or moon blocking the star's light
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SphereOccluder {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+// A flat ring (annulus) blocking part of the star's light
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RingOccluder {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub style: RingStyle, // Density profile between the two radii
+}
+
+// Bodies that cast shadows this frame. Fixed capacity so `Uniforms` stays
+// `Copy` and can be handed to the rasterizer threads as is.
+#[derive(Clone, Copy, Debug)]
+pub struct Occluders {
+    spheres: [SphereOccluder; MAX_SPHERE_OCCLUDERS],
+    sphere_count: usize,
+    rings: [RingOccluder; MAX_RING_OCCLUDERS],
+    ring_count: usize,
+}
+
+impl Default for Occluders {
+    fn default() -> Self {
+        Occluders {
+            spheres: [SphereOccluder::default(); MAX_SPHERE_OCCLUDERS],
+            sphere_count: 0,
+            rings: [RingOccluder::default(); MAX_RING_OCCLUDERS],
+            ring_count: 0,
+        }
+    }
+}
+
+impl Occluders {
+    // Returns false (and ignores the occluder) once the list is full
+    pub fn push_sphere(&mut self, occluder: SphereOccluder) -> bool {
+        if self.sphere_count == MAX_SPHERE_OCCLUDERS {
+            return false;
+        }
+        self.spheres[self.sphere_count] = occluder;
+        self.sphere_count += 1;
+        true
+    }
+
+    pub fn push_ring(&mut self, occluder: RingOccluder) -> bool {
+        if self.ring_count == MAX_RING_OCCLUDERS {
+            return false;
+        }
+        self.rings[self.ring_count] = occluder;
+        self.ring_count += 1;
+        true
+    }
+
+    pub fn spheres(&self) -> &[SphereOccluder] {
+        &self.spheres[..self.sphere_count]
+    }
+
+    pub fn rings(&self) -> &[RingOccluder] {
+        &self.rings[..self.ring_count]
+    }
+
+    // Fraction of the star visible from `point`, in [0, 1]. The star is a
+    // disk of `light_radius`, so sphere shadows get an umbra and a penumbra;
+    // ring shadows are sampled along the ray to the star's center.
+    pub fn visibility(&self, point: Vec3, light_position: Vec3, light_radius: f32) -> f32 {
+        let to_light = light_position - point;
+        let light_distance = to_light.magnitude();
+        if light_distance <= f32::EPSILON {
+            return 1.0;
+        }
+        let direction = to_light / light_distance;
+
+        let mut visibility = 1.0;
+
+        for sphere in self.spheres() {
+            visibility *= sphere_visibility(sphere, point, direction, light_distance, light_radius);
+            if visibility <= 0.0 {
+                return 0.0;
+            }
+        }
+
+        for ring in self.rings() {
+            visibility *= 1.0 - ring_coverage(ring, point, direction, light_distance);
+        }
+
+        visibility
+    }
+}
+
+fn sphere_visibility(sphere: &SphereOccluder, point: Vec3, direction: Vec3, light_distance: f32, light_radius: f32) -> f32 {
+    let to_center = sphere.center - point;
+
+    // Points on (or inside) the sphere are its own surface; the night side
+    // is already handled by the diffuse term
+    if to_center.magnitude_squared() <= (sphere.radius * 1.001).powi(2) {
+        return 1.0;
+    }
+
+    // Only occluders between the point and the star count
+    let along = dot(&to_center, &direction);
+    if along <= 0.0 || along >= light_distance {
+        return 1.0;
+    }
+
+    // Angular radii of the star and the occluder, and the angle between them
+    let separation = (to_center - direction * along).magnitude() / along;
+    let occluder_size = sphere.radius / along;
+    let light_size = (light_radius / light_distance).max(1e-4);
+
+    let umbra = occluder_size - light_size;
+    let penumbra = occluder_size + light_size;
+    if separation >= penumbra {
+        return 1.0;
+    }
+
+    // Smaller than the star: only an annular eclipse, never fully dark
+    let darkest = if occluder_size < light_size {
+        1.0 - (occluder_size / light_size).powi(2)
+    } else {
+        0.0
+    };
+
+    let t = ((separation - umbra) / (penumbra - umbra)).clamp(0.0, 1.0);
+    darkest + (1.0 - darkest) * t * t * (3.0 - 2.0 * t)
+}
+
+fn ring_coverage(ring: &RingOccluder, point: Vec3, direction: Vec3, light_distance: f32) -> f32 {
+    let facing = dot(&direction, &ring.normal);
+    if facing.abs() <= f32::EPSILON {
+        return 0.0;
+    }
+
+    // Points on the ring plane itself don't shadow themselves
+    let height = dot(&(ring.center - point), &ring.normal);
+    if height.abs() <= ring.outer_radius * 1e-3 {
+        return 0.0;
+    }
+
+    let t = height / facing;
+    if t <= 0.0 || t >= light_distance {
+        return 0.0;
+    }
+
+    let radial = (point + direction * t - ring.center).magnitude();
+    if radial < ring.inner_radius || radial > ring.outer_radius {
+        return 0.0;
+    }
+
+    // The same banded density the ring is drawn with
+    let u = (radial - ring.inner_radius) / (ring.outer_radius - ring.inner_radius);
+    ring.style.density(u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    const LIGHT: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+
+    fn occluders_with_moon() -> Occluders {
+        let mut occluders = Occluders::default();
+        occluders.push_sphere(SphereOccluder { center: Vec3::new(50.0, 0.0, 0.0), radius: 5.0 });
+        occluders
+    }
+
+    #[test]
+    fn sphere_between_point_and_light_casts_umbra_and_penumbra() {
+        let occluders = occluders_with_moon();
+
+        // Directly behind the moon
+        assert_eq!(occluders.visibility(Vec3::new(60.0, 0.0, 0.0), LIGHT, 1.0), 0.0);
+        // Far off axis
+        assert_eq!(occluders.visibility(Vec3::new(60.0, 20.0, 0.0), LIGHT, 1.0), 1.0);
+        // Between the moon and the light
+        assert_eq!(occluders.visibility(Vec3::new(40.0, 0.0, 0.0), LIGHT, 1.0), 1.0);
+
+        // Near the shadow's edge the star is partially hidden
+        let edge = occluders.visibility(Vec3::new(60.0, 6.0, 0.0), LIGHT, 1.0);
+        assert!(edge > 0.0 && edge < 1.0, "penumbra visibility {}", edge);
+    }
+
+    #[test]
+    fn point_on_occluder_surface_is_not_self_shadowed() {
+        let occluders = occluders_with_moon();
+        assert_eq!(occluders.visibility(Vec3::new(55.0, 0.0, 0.0), LIGHT, 1.0), 1.0);
+    }
+
+    #[test]
+    fn ring_shadow_covers_only_the_annulus() {
+        let mut occluders = Occluders::default();
+        occluders.push_ring(RingOccluder {
+            center: Vec3::new(0.0, 10.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            inner_radius: 2.0,
+            outer_radius: 4.0,
+            style: RingStyle::new(Color::new(200, 200, 200), 3.0, 0.8),
+        });
+        let light = Vec3::new(0.0, 1000.0, 0.0);
+
+        let under_ring = occluders.visibility(Vec3::new(3.0, 0.0, 0.0), light, 0.0);
+        let under_gap = occluders.visibility(Vec3::new(1.0, 0.0, 0.0), light, 0.0);
+        let outside = occluders.visibility(Vec3::new(5.0, 0.0, 0.0), light, 0.0);
+        let on_ring = occluders.visibility(Vec3::new(3.0, 10.0, 0.0), light, 0.0);
+
+        assert!((0.2..1.0).contains(&under_ring));
+        assert_eq!(under_gap, 1.0);
+        assert_eq!(outside, 1.0);
+        assert_eq!(on_ring, 1.0);
+    }
+}
@@ -0,0 +1,32 @@
+use nalgebra_glm::Vec3;
+use crate::mesh::Mesh;
+use crate::obj::Obj;
+use crate::shaders::{FragmentShader, SpaceshipShader};
+
+// A rigid model placed in the scene, such as the spaceship
+pub struct Ship {
+    pub mesh: Mesh,
+    pub shader: Box<dyn FragmentShader>,
+    pub scale: f32,
+    pub rotation: Vec3, // Euler angles of the model within its node
+}
+
+impl Ship {
+    pub fn new(mesh: Mesh, scale: f32) -> Self {
+        Ship {
+            mesh,
+            shader: Box::new(SpaceshipShader),
+            scale,
+            rotation: Vec3::zeros(),
+        }
+    }
+
+    pub fn load(filename: &str, scale: f32) -> Result<Self, tobj::LoadError> {
+        Ok(Ship::new(Obj::load(filename)?.get_mesh(), scale))
+    }
+
+    pub fn with_shader(mut self, shader: Box<dyn FragmentShader>) -> Self {
+        self.shader = shader;
+        self
+    }
+}
@@ -1,9 +1,10 @@
 use nalgebra_glm::{Vec2, Vec3};
 use std::f32::consts::PI;
 use crate::vertex::Vertex;
+use crate::mesh::Mesh;
 
 pub struct Sphere {
-    pub vertices: Vec<Vertex>,
+    pub mesh: Mesh,
 }
 
 impl Sphere {
@@ -37,32 +38,52 @@ impl Sphere {
             }
         }
 
-        // Generate triangulated faces
-        let mut triangulated_vertices = Vec::new();
-        
+        // Generate triangle indices into the shared vertex grid
+        let mut indices = Vec::with_capacity((latitude_segments * longitude_segments * 6) as usize);
+
         for lat in 0..latitude_segments {
             for lon in 0..longitude_segments {
-                let first = (lat * (longitude_segments + 1) + lon) as usize;
-                let second = first + longitude_segments as usize + 1;
+                let first = lat * (longitude_segments + 1) + lon;
+                let second = first + longitude_segments + 1;
 
                 // First triangle
-                triangulated_vertices.push(vertices[first].clone());
-                triangulated_vertices.push(vertices[second].clone());
-                triangulated_vertices.push(vertices[first + 1].clone());
+                indices.extend_from_slice(&[first, second, first + 1]);
 
                 // Second triangle
-                triangulated_vertices.push(vertices[second].clone());
-                triangulated_vertices.push(vertices[second + 1].clone());
-                triangulated_vertices.push(vertices[first + 1].clone());
+                indices.extend_from_slice(&[second, second + 1, first + 1]);
             }
         }
 
         Sphere {
-            vertices: triangulated_vertices,
+            mesh: Mesh::new(vertices, indices),
         }
     }
 
-    pub fn get_vertex_array(&self) -> &Vec<Vertex> {
-        &self.vertices
+    pub fn get_mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighboring_triangles_share_grid_vertices() {
+        let sphere = Sphere::new(1.0, 8, 16);
+        let mesh = sphere.get_mesh();
+
+        // One vertex per grid point, instead of three per triangle
+        assert_eq!(mesh.vertices.len(), 9 * 17);
+        assert_eq!(mesh.indices.len(), 8 * 16 * 6);
+
+        let mut uses = vec![0; mesh.vertices.len()];
+        for &index in &mesh.indices {
+            uses[index as usize] += 1;
+        }
+        assert!(uses.iter().all(|&count| count > 0));
+
+        // An interior grid point is a corner of all six triangles around it
+        assert_eq!(uses[4 * 17 + 8], 6);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,78 @@
+use std::sync::Mutex;
+use std::thread;
+use crate::framebuffer::Framebuffer;
+use crate::vertex::Vertex;
+use crate::shaders::{FragmentShader, Uniforms};
+use crate::triangle::rasterize_triangle;
+
+// Shading state of one queued mesh
+pub struct DrawCall<'a> {
+    pub shader: &'a dyn FragmentShader,
+    pub uniforms: Uniforms,
+    pub time: f32,
+}
+
+// A clipped, culled, screen-space triangle waiting to be rasterized,
+// with the index of the draw call it came from
+pub struct ScreenTriangle {
+    pub vertices: [Vertex; 3],
+    pub draw: usize,
+}
+
+// Bin the frame's triangles into tiles of `tile_height` rows and rasterize
+// the tiles in parallel. Each tile processes its triangles in submission
+// order, so every pixel sees the same sequence of depth tests as in the
+// single-threaded path and the output is bit-identical.
+pub fn rasterize_tiled(
+    framebuffer: &mut Framebuffer,
+    draws: &[DrawCall],
+    triangles: &[ScreenTriangle],
+    threads: usize,
+    tile_height: usize,
+) {
+    if framebuffer.width == 0 || framebuffer.height == 0 {
+        return;
+    }
+
+    let tile_height = tile_height.max(1);
+    let tile_count = framebuffer.height.div_ceil(tile_height);
+
+    // Binning Stage - every triangle goes to each tile its rows overlap
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); tile_count];
+    for (index, triangle) in triangles.iter().enumerate() {
+        let [a, b, c] = &triangle.vertices;
+        let min_y = a.transformed_position.y.min(b.transformed_position.y).min(c.transformed_position.y).floor() as i32;
+        let max_y = a.transformed_position.y.max(b.transformed_position.y).max(c.transformed_position.y).ceil() as i32;
+
+        if max_y < 0 || min_y >= framebuffer.height as i32 {
+            continue;
+        }
+
+        let first = min_y.max(0) as usize / tile_height;
+        let last = (max_y as usize).min(framebuffer.height - 1) / tile_height;
+        for bin in &mut bins[first..=last] {
+            bin.push(index);
+        }
+    }
+
+    // Workers pull the next unprocessed tile until none are left
+    let work = Mutex::new(framebuffer.tiles(tile_height).into_iter().zip(bins));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let next = work.lock().unwrap().next();
+                let Some((mut tile, bin)) = next else {
+                    break;
+                };
+
+                for &index in &bin {
+                    let triangle = &triangles[index];
+                    let draw = &draws[triangle.draw];
+                    let [v1, v2, v3] = &triangle.vertices;
+                    rasterize_triangle(&mut tile, v1, v2, v3, draw.shader, &draw.uniforms, draw.time);
+                }
+            });
+        }
+    });
+}
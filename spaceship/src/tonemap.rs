@@ -0,0 +1,126 @@
+use std::sync::OnceLock;
+use crate::color::{linear_to_srgb, Color, LinearColor};
+
+// Operator compressing HDR values into the displayable [0, 1] range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapper {
+    Reinhard,
+    Aces,   // Narkowicz's fit of the ACES filmic curve
+    Filmic, // Hable's Uncharted 2 curve
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 3] = [ToneMapper::Reinhard, ToneMapper::Aces, ToneMapper::Filmic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::Reinhard => "Reinhard",
+            ToneMapper::Aces => "ACES",
+            ToneMapper::Filmic => "Filmic",
+        }
+    }
+
+    // The following operator, wrapping around
+    pub fn next(self) -> Self {
+        let index = ToneMapper::ALL.iter().position(|&op| op == self).unwrap_or(0);
+        ToneMapper::ALL[(index + 1) % ToneMapper::ALL.len()]
+    }
+
+    // Maps one linear channel value to [0, 1]
+    pub fn apply(self, x: f32) -> f32 {
+        let x = x.max(0.0);
+        match self {
+            ToneMapper::Reinhard => x / (1.0 + x),
+            ToneMapper::Aces => {
+                let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                mapped.clamp(0.0, 1.0)
+            }
+            ToneMapper::Filmic => {
+                const EXPOSURE_BIAS: f32 = 2.0;
+                const WHITE_POINT: f32 = 11.2;
+                (hable(x * EXPOSURE_BIAS) / hable(WHITE_POINT)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15; // Shoulder strength
+    const B: f32 = 0.50; // Linear strength
+    const C: f32 = 0.10; // Linear angle
+    const D: f32 = 0.20; // Toe strength
+    const E: f32 = 0.02; // Toe numerator
+    const F: f32 = 0.30; // Toe denominator
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+// Settings of the resolve pass from the HDR target to display colors
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapping {
+    pub operator: ToneMapper,
+    pub exposure: f32, // Linear multiplier applied before the operator
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: ToneMapper::Aces,
+            exposure: 1.6,
+        }
+    }
+}
+
+impl ToneMapping {
+    // Exposure, tone mapping and sRGB encoding of one pixel
+    pub fn map(&self, color: LinearColor) -> Color {
+        let channel = |value: f32| encode_srgb(self.operator.apply(value * self.exposure));
+        Color::new(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
+
+// Table size for the sRGB encode; fine enough that neighboring entries
+// never skip an 8-bit step in the dark end
+const SRGB_TABLE_SIZE: usize = 4096;
+
+fn encode_srgb(value: f32) -> u8 {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        (0..SRGB_TABLE_SIZE)
+            .map(|i| {
+                let linear = i as f32 / (SRGB_TABLE_SIZE - 1) as f32;
+                (linear_to_srgb(linear) * 255.0).round() as u8
+            })
+            .collect()
+    });
+
+    let index = (value.clamp(0.0, 1.0) * (SRGB_TABLE_SIZE - 1) as f32).round() as usize;
+    table[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        for operator in ToneMapper::ALL {
+            let mut previous = operator.apply(0.0);
+            assert!(previous.abs() < 1e-3, "{} maps black to {}", operator.name(), previous);
+
+            for i in 1..=400 {
+                let value = operator.apply(i as f32 * 0.05);
+                assert!(value >= previous, "{} is not monotonic", operator.name());
+                assert!(value <= 1.0);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_encoding_round_trips_8_bit_colors() {
+        for value in 0..=255u8 {
+            let linear = LinearColor::from(Color::new(value, value, value)).r;
+            assert_eq!(encode_srgb(linear), value);
+        }
+    }
+}
@@ -1,82 +1,456 @@
-use nalgebra_glm::{Vec3, dot};
+use nalgebra_glm::Vec3;
 use crate::fragment::Fragment;
+use crate::framebuffer::{sample_pattern, Tile};
 use crate::vertex::Vertex;
-use crate::color::Color;
-use crate::planets::PlanetType;
+use crate::color::LinearColor;
+use crate::shaders::{FragmentInput, FragmentShader, Uniforms};
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    triangle_with_shader(v1, v2, v3, None, 0.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CullMode {
+    None,  // Double-sided geometry (rings)
+    Back,  // Skip triangles facing away from the camera
+    Front, // Skip triangles facing the camera
 }
 
-pub fn triangle_with_shader(v1: &Vertex, v2: &Vertex, v3: &Vertex, shader_type: Option<PlanetType>, time: f32) -> Vec<Fragment> {
+// Winding of front faces as seen in normalized device coordinates (y up)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontFace {
+    Clockwise,
+    CounterClockwise,
+}
+
+// Decide from the screen-space signed area whether a triangle is discarded
+pub fn is_culled(v1: &Vertex, v2: &Vertex, v3: &Vertex, cull_mode: CullMode, front_face: FrontFace) -> bool {
+    if cull_mode == CullMode::None {
+        return false;
+    }
+
+    let signed_area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+
+    // The viewport flips y, so counter-clockwise in NDC is a positive area on screen
+    let is_front_facing = match front_face {
+        FrontFace::CounterClockwise => signed_area > 0.0,
+        FrontFace::Clockwise => signed_area < 0.0,
+    };
+
+    match cull_mode {
+        CullMode::None => false,
+        CullMode::Back => !is_front_facing,
+        CullMode::Front => is_front_facing,
+    }
+}
+
+// Fragment-collecting rasterizer; kept as the reference path for benchmarks
+pub fn triangle_with_shader(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    shader: &dyn FragmentShader,
+    uniforms: &Uniforms,
+    time: f32,
+) -> Vec<Fragment> {
     let mut fragments = Vec::new();
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-    let triangle_area = edge_function(&a, &b, &c);
+    let Some(setup) = TriangleSetup::new(&a, &b, &c) else {
+        return fragments;
+    };
+    let (min_x, min_y, max_x, max_y) = setup.bounds;
 
     // Iterate over each pixel in the bounding box
+    let mut row = setup.edges_at(min_x, min_y);
     for y in min_y..=max_y {
+        let mut edges = row;
         for x in min_x..=max_x {
-            let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-
-            // Calculate barycentric coordinates
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
-
-            // Check if the point is inside the triangle
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
-        // Interpolate attributes
-        let normal = (v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3).normalize();
-        let uv = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
-        let world_pos = v1.position * w1 + v2.position * w2 + v3.position * w3;
-
-        let lit_color = if let Some(planet_type) = &shader_type {
-          // Use planetary shader
-          match planet_type {
-            PlanetType::Star => crate::planets::star_shader(world_pos, normal, uv, time),
-            PlanetType::RockyPlanet => crate::planets::rocky_planet_shader(world_pos, normal, uv, time),
-            PlanetType::GasGiant => crate::planets::gas_giant_shader(world_pos, normal, uv, time),
-            PlanetType::IcePlanet => crate::planets::ice_planet_shader(world_pos, normal, uv, time),
-            PlanetType::VolcanicPlanet => crate::planets::volcanic_planet_shader(world_pos, normal, uv, time),
-            PlanetType::RingedPlanet => crate::planets::ringed_planet_shader(world_pos, normal, uv, time),
-          }
-        } else {
-          // Default lighting for spaceship
-          let light_dir = Vec3::new(0.0, 0.0, -1.0);
-          let intensity = dot(&normal, &light_dir).max(0.0);
-          let base_color = Color::new(100, 150, 255);
-          base_color * intensity.max(0.3)
-        };
-
-        let depth = a.z;                fragments.push(Fragment::new(x as f32, y as f32, lit_color, depth));
+            // Check if the pixel center is inside the triangle
+            if setup.covers(edges) {
+                let weights = setup.weights(edges, 1);
+                // Screen-space z is affine after the perspective divide
+                let depth = a.z * weights.0 + b.z * weights.1 + c.z * weights.2;
+                let lit_color = shade_fragment(v1, v2, v3, weights, shader, uniforms, time);
+
+                fragments.push(Fragment::new(x as f32, y as f32, lit_color, depth));
             }
+            setup.step_x(&mut edges);
         }
+        setup.step_y(&mut row);
     }
 
     fragments
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// Streaming rasterizer: depth-tests every covered sample of the tile before
+// shading and writes the surviving fragments straight into the tile. With
+// multisampling the shader still runs once per pixel, at the centroid of
+// the samples that passed, and its color goes to each of them.
+pub fn rasterize_triangle(
+    tile: &mut Tile,
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    shader: &dyn FragmentShader,
+    uniforms: &Uniforms,
+    time: f32,
+) {
+    if tile.width == 0 || tile.height == 0 {
+        return;
+    }
+
+    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let Some(setup) = TriangleSetup::new(&a, &b, &c) else {
+        return;
+    };
+
+    // Only visit pixels that belong to the tile
+    let (min_x, min_y, max_x, max_y) = setup.bounds;
+    let min_x = min_x.max(0);
+    let min_y = min_y.max(tile.y_start as i32);
+    let max_x = max_x.min(tile.width as i32 - 1);
+    let max_y = max_y.min(tile.y_end() as i32 - 1);
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    let blend_mode = shader.blend_mode();
+    let pattern = sample_pattern(tile.samples);
+    let mut offsets = [[0i64; 3]; 8];
+    for (offset, &sample) in offsets.iter_mut().zip(pattern) {
+        *offset = setup.sample_offset(sample);
+    }
+
+    let mut row = setup.edges_at(min_x, min_y);
+    for y in min_y..=max_y {
+        let mut edges = row;
+        for x in min_x..=max_x {
+            let (px, py) = (x as usize, y as usize);
+            let mut covered = [false; 8];
+            let mut depths = [0.0; 8];
+            let mut centroid = [0i64; 3];
+            let mut count = 0;
+
+            for (sample, offset) in offsets[..pattern.len()].iter().enumerate() {
+                let sample_edges = [edges[0] + offset[0], edges[1] + offset[1], edges[2] + offset[2]];
+                if !setup.covers(sample_edges) {
+                    continue;
+                }
+
+                let (w1, w2, w3) = setup.weights(sample_edges, 1);
+                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+                // Early-z: occluded samples never reach the planet shader
+                if tile.depth_test(px, py, sample, depth) {
+                    covered[sample] = true;
+                    depths[sample] = depth;
+                    for (sum, edge) in centroid.iter_mut().zip(sample_edges) {
+                        *sum += edge;
+                    }
+                    count += 1;
+                }
+            }
+
+            if count > 0 {
+                // Edge values are linear, so their mean is the centroid's
+                let weights = setup.weights(centroid, count);
+                let lit_color = shade_fragment(v1, v2, v3, weights, shader, uniforms, time);
 
-    (min_x, min_y, max_x, max_y)
+                for (sample, &depth) in depths.iter().enumerate().take(pattern.len()) {
+                    if covered[sample] {
+                        tile.write_sample(px, py, sample, lit_color, depth, blend_mode);
+                    }
+                }
+            }
+            setup.step_x(&mut edges);
+        }
+        setup.step_y(&mut row);
+    }
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
-    let w1 = edge_function(b, c, p) / area;
-    let w2 = edge_function(c, a, p) / area;
-    let w3 = edge_function(a, b, p) / area;
+// Sub-pixel precision of the rasterizer: vertices are snapped to 1/256 of
+// a pixel, so edge functions are exact integers
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: f32 = (1 << SUBPIXEL_BITS) as f32;
+
+fn to_fixed(value: f32) -> i64 {
+    (value * SUBPIXEL_SCALE).round() as i64
+}
 
-    (w1, w2, w3)
+// Fixed-point edge functions of a screen-space triangle, oriented so the
+// inside is positive whatever the winding. A sample exactly on an edge
+// belongs to the triangle only if that is a top or left edge, so triangles
+// sharing an edge cover each of its samples exactly once.
+struct TriangleSetup {
+    origins: [(i64, i64); 3], // Start point of the edge opposite each vertex
+    deltas: [(i64, i64); 3],  // Edge vectors, flipped for negative areas
+    biases: [i64; 3],         // 1 on top-left edges, which include their samples
+    area: i64,
+    bounds: (i32, i32, i32, i32),
+}
+
+impl TriangleSetup {
+    fn new(a: &Vec3, b: &Vec3, c: &Vec3) -> Option<Self> {
+        let points = [a, b, c].map(|p| (to_fixed(p.x), to_fixed(p.y)));
+        let [pa, pb, pc] = points;
+
+        let area = fixed_edge(pa, pb, pc);
+        if area == 0 {
+            return None;
+        }
+        let sign = area.signum();
+
+        // Edge i is opposite vertex i, as in the barycentric weights
+        let edges = [(pb, pc), (pc, pa), (pa, pb)];
+        let origins = edges.map(|(start, _)| start);
+        let deltas = edges.map(|(start, end)| ((end.0 - start.0) * sign, (end.1 - start.1) * sign));
+
+        // The edge function grows along (dy, -dx). Left edges have the
+        // inside to their right; top edges are horizontal with the inside
+        // below (y points down).
+        let biases = deltas.map(|(dx, dy)| {
+            let (inward_x, inward_y) = (dy, -dx);
+            if inward_x > 0 || (inward_x == 0 && inward_y > 0) { 1 } else { 0 }
+        });
+
+        let min_x = points.iter().map(|p| p.0).min().unwrap();
+        let min_y = points.iter().map(|p| p.1).min().unwrap();
+        let max_x = points.iter().map(|p| p.0).max().unwrap();
+        let max_y = points.iter().map(|p| p.1).max().unwrap();
+        let scale = 1 << SUBPIXEL_BITS;
+        let bounds = (
+            min_x.div_euclid(scale) as i32,
+            min_y.div_euclid(scale) as i32,
+            max_x.div_euclid(scale) as i32,
+            max_y.div_euclid(scale) as i32,
+        );
+
+        Some(TriangleSetup { origins, deltas, biases, area: area.abs(), bounds })
+    }
+
+    // Edge values at the center of pixel (x, y)
+    fn edges_at(&self, x: i32, y: i32) -> [i64; 3] {
+        let half = 1 << (SUBPIXEL_BITS - 1);
+        let point = (((x as i64) << SUBPIXEL_BITS) + half, ((y as i64) << SUBPIXEL_BITS) + half);
+        std::array::from_fn(|i| {
+            let (origin, (dx, dy)) = (self.origins[i], self.deltas[i]);
+            (point.0 - origin.0) * dy - (point.1 - origin.1) * dx
+        })
+    }
+
+    // Change of the edge values from the pixel center to a sample
+    fn sample_offset(&self, (offset_x, offset_y): (f32, f32)) -> [i64; 3] {
+        let (offset_x, offset_y) = (to_fixed(offset_x), to_fixed(offset_y));
+        self.deltas.map(|(dx, dy)| offset_x * dy - offset_y * dx)
+    }
+
+    // Incremental stepping one pixel right and one pixel down
+    fn step_x(&self, edges: &mut [i64; 3]) {
+        for (edge, (_, dy)) in edges.iter_mut().zip(self.deltas) {
+            *edge += dy << SUBPIXEL_BITS;
+        }
+    }
+
+    fn step_y(&self, edges: &mut [i64; 3]) {
+        for (edge, (dx, _)) in edges.iter_mut().zip(self.deltas) {
+            *edge -= dx << SUBPIXEL_BITS;
+        }
+    }
+
+    fn covers(&self, edges: [i64; 3]) -> bool {
+        edges.iter().zip(self.biases).all(|(&edge, bias)| edge + bias > 0)
+    }
+
+    // Barycentric weights from edge values summed over `count` samples
+    fn weights(&self, edges: [i64; 3], count: i64) -> (f32, f32, f32) {
+        let total = (self.area * count) as f64;
+        (
+            (edges[0] as f64 / total) as f32,
+            (edges[1] as f64 / total) as f32,
+            (edges[2] as f64 / total) as f32,
+        )
+    }
+}
+
+// Same as `edge_function`, on fixed-point coordinates
+fn fixed_edge(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> i64 {
+    (c.0 - a.0) * (b.1 - a.1) - (c.1 - a.1) * (b.0 - a.0)
+}
+
+// Interpolate the vertex attributes at the given barycentric weights and run the shader
+fn shade_fragment(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    weights: (f32, f32, f32),
+    shader: &dyn FragmentShader,
+    uniforms: &Uniforms,
+    time: f32,
+) -> LinearColor {
+    let (w1, w2, w3) = weights;
+
+    // Perspective-correct weights using 1/w of each vertex
+    let (p1, p2, p3) = perspective_correct_weights(
+        w1, w2, w3,
+        1.0 / v1.clip_position.w,
+        1.0 / v2.clip_position.w,
+        1.0 / v3.clip_position.w,
+    );
+
+    // Interpolate attributes
+    let input = FragmentInput {
+        position: v1.position * p1 + v2.position * p2 + v3.position * p3,
+        world_position: v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3,
+        normal: (v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3).normalize(),
+        uv: v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3,
+        time,
+        uniforms,
+    };
+
+    shader.shade(&input)
+}
+
+fn perspective_correct_weights(w1: f32, w2: f32, w3: f32, inv_w1: f32, inv_w2: f32, inv_w3: f32) -> (f32, f32, f32) {
+    let p1 = w1 * inv_w1;
+    let p2 = w2 * inv_w2;
+    let p3 = w3 * inv_w3;
+    let sum = p1 + p2 + p3;
+
+    (p1 / sum, p2 / sum, p3 / sum)
 }
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec4, Mat4};
+    use crate::shaders::{Blended, SpaceshipShader};
+    use crate::lighting::PointLight;
+    use crate::shadows::Occluders;
+    use crate::framebuffer::{BlendMode, Framebuffer};
+
+    fn screen_vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, -1.0), Vec2::new(0.0, 0.0));
+        vertex.transformed_position = Vec3::new(x, y, z);
+        vertex.clip_position = Vec4::new(x, y, z, w);
+        vertex
+    }
+
+    fn uniforms() -> Uniforms {
+        Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: Mat4::identity(),
+            projection_matrix: Mat4::identity(),
+            viewport_matrix: Mat4::identity(),
+            camera_position: Vec3::new(0.0, 0.0, -10.0),
+            light: PointLight::default(),
+            occluders: Occluders::default(),
+        }
+    }
+
+    // Adds 1 to every sample it covers, so overlaps show up as 2
+    fn coverage_count(size: usize, samples: usize, triangles: &[[(f32, f32); 3]]) -> Framebuffer {
+        let shader = Blended(|_: &FragmentInput| LinearColor::new(1.0, 1.0, 1.0), BlendMode::Additive);
+        let mut framebuffer = Framebuffer::new(size, size);
+        framebuffer.set_samples(samples);
+        framebuffer.clear();
+
+        let uniforms = uniforms();
+        for triangle in triangles {
+            let [v1, v2, v3] = triangle.map(|(x, y)| screen_vertex(x, y, 0.5, 1.0));
+            rasterize_triangle(&mut framebuffer.full_tile(), &v1, &v2, &v3, &shader, &uniforms, 0.0);
+        }
+        framebuffer.resolve_samples();
+        framebuffer
+    }
+
+    #[test]
+    fn depth_is_interpolated_per_pixel() {
+        let v1 = screen_vertex(0.0, 0.0, 0.2, 1.0);
+        let v2 = screen_vertex(100.0, 0.0, 0.8, 1.0);
+        let v3 = screen_vertex(0.0, 100.0, 0.2, 1.0);
+
+        let uniforms = uniforms();
+        let fragments = triangle_with_shader(&v1, &v2, &v3, &SpaceshipShader, &uniforms, 0.0);
+        let left = fragments.iter().find(|f| f.position.x == 0.0 && f.position.y == 0.0).unwrap();
+        let right = fragments.iter().find(|f| f.position.x == 90.0 && f.position.y == 0.0).unwrap();
+
+        assert!((left.depth - 0.203).abs() < 1e-3);
+        assert!((right.depth - 0.743).abs() < 1e-3);
+    }
+
+    #[test]
+    fn culling_uses_screen_space_winding() {
+        // Counter-clockwise in NDC (y up). The viewport's y flip keeps the
+        // triangle counter-clockwise as seen on screen, and with y down
+        // `edge_function` gives it a positive area, which is what
+        // `FrontFace::CounterClockwise` treats as front-facing
+        let v1 = screen_vertex(0.0, 0.0, 0.5, 1.0);
+        let v2 = screen_vertex(0.0, 10.0, 0.5, 1.0);
+        let v3 = screen_vertex(10.0, 0.0, 0.5, 1.0);
+
+        assert!(!is_culled(&v1, &v2, &v3, CullMode::Back, FrontFace::CounterClockwise));
+        assert!(is_culled(&v1, &v2, &v3, CullMode::Front, FrontFace::CounterClockwise));
+        assert!(is_culled(&v1, &v3, &v2, CullMode::Back, FrontFace::CounterClockwise));
+        assert!(is_culled(&v1, &v2, &v3, CullMode::Back, FrontFace::Clockwise));
+        assert!(!is_culled(&v1, &v2, &v3, CullMode::None, FrontFace::Clockwise));
+        assert!(!is_culled(&v1, &v3, &v2, CullMode::None, FrontFace::CounterClockwise));
+    }
+
+    #[test]
+    fn perspective_weights_favor_nearer_vertices() {
+        // Screen-space midpoint of an edge whose far end is 3x further away
+        let (p1, p2, p3) = perspective_correct_weights(0.5, 0.5, 0.0, 1.0, 1.0 / 3.0, 1.0);
+
+        assert!((p1 - 0.75).abs() < 1e-6);
+        assert!((p2 - 0.25).abs() < 1e-6);
+        assert_eq!(p3, 0.0);
+    }
+
+    #[test]
+    fn quad_diagonal_is_covered_exactly_once() {
+        // Pixel centers land exactly on every edge, in both windings
+        for (a, b, c, d) in [
+            ((2.5, 2.5), (18.5, 2.5), (18.5, 18.5), (2.5, 18.5)),
+            ((2.5, 2.5), (2.5, 18.5), (18.5, 18.5), (18.5, 2.5)),
+        ] {
+            let framebuffer = coverage_count(20, 1, &[[a, b, c], [a, c, d]]);
+            let mut covered = 0;
+            for (i, color) in framebuffer.color.iter().enumerate() {
+                let (x, y) = ((i % 20) as f32 + 0.5, (i / 20) as f32 + 0.5);
+                assert!(color.r == 0.0 || color.r == 1.0, "pixel {} drawn {} times", i, color.r);
+                // Only the top and left sides of the quad own their pixels
+                let inside = (2.5..18.5).contains(&x) && (2.5..18.5).contains(&y);
+                assert_eq!(color.r == 1.0, inside, "pixel {}", i);
+                covered += color.r as usize;
+            }
+            assert_eq!(covered, 16 * 16);
+        }
+    }
+
+    #[test]
+    fn triangle_fan_has_no_cracks_or_overlaps() {
+        // Off-grid vertices around an off-grid center, at every sample count
+        let center = (16.3, 15.7);
+        let rim: Vec<(f32, f32)> = (0..13)
+            .map(|i| {
+                let angle = i as f32 / 13.0 * std::f32::consts::TAU;
+                (center.0 + 13.1 * angle.cos(), center.1 + 12.9 * angle.sin())
+            })
+            .collect();
+        let triangles: Vec<_> = (0..rim.len()).map(|i| [center, rim[i], rim[(i + 1) % rim.len()]]).collect();
+
+        for samples in [1, 4, 8] {
+            let framebuffer = coverage_count(32, samples, &triangles);
+            for (i, color) in framebuffer.color.iter().enumerate() {
+                assert!(color.r <= 1.0 + 1e-6, "pixel {} overdrawn at {}x", i, samples);
+
+                // Pixels well inside the polygon must be fully covered
+                let (x, y) = ((i % 32) as f32 + 0.5, (i / 32) as f32 + 0.5);
+                if (x - center.0).hypot(y - center.1) < 11.0 {
+                    assert!((color.r - 1.0).abs() < 1e-6, "crack at pixel {} at {}x", i, samples);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -7,6 +7,8 @@ pub struct Vertex {
     pub normal: Vec3,
     pub tex_coords: Vec2,
     pub color: Color,
+    pub world_position: Vec3,
+    pub clip_position: Vec4,
     pub transformed_position: Vec3,
     pub transformed_normal: Vec3,
 }
@@ -18,8 +20,28 @@ impl Vertex {
             normal,
             tex_coords,
             color: Color::white(),
+            world_position: position,
+            clip_position: position.push(1.0),
             transformed_position: position,
             transformed_normal: normal,
         }
     }
+
+    // Linear interpolation of every attribute, used when clipping splits an edge
+    pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+        Vertex {
+            position: self.position + (other.position - self.position) * t,
+            normal: self.normal + (other.normal - self.normal) * t,
+            tex_coords: self.tex_coords + (other.tex_coords - self.tex_coords) * t,
+            color: Color::new(
+                (self.color.r as f32 + (other.color.r as f32 - self.color.r as f32) * t) as u8,
+                (self.color.g as f32 + (other.color.g as f32 - self.color.g as f32) * t) as u8,
+                (self.color.b as f32 + (other.color.b as f32 - self.color.b as f32) * t) as u8,
+            ),
+            world_position: self.world_position + (other.world_position - self.world_position) * t,
+            clip_position: self.clip_position + (other.clip_position - self.clip_position) * t,
+            transformed_position: self.transformed_position + (other.transformed_position - self.transformed_position) * t,
+            transformed_normal: self.transformed_normal + (other.transformed_normal - self.transformed_normal) * t,
+        }
+    }
 }
@@ -0,0 +1,185 @@
+// Golden-image regression tests for the planet, moon and ring shaders.
+//
+// Each case renders one body at a fixed time and camera into an offscreen
+// framebuffer and compares it with tests/golden/<name>.png. On a mismatch
+// the rendered image and a diff (mismatching pixels in red over a dimmed
+// reference) are written to the cargo test temp dir.
+//
+// Regenerate the references after an intended visual change with
+// UPDATE_GOLDEN=1 cargo test --test golden
+
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+use nalgebra_glm::Vec3;
+use spaceship::camera::Camera;
+use spaceship::framebuffer::Framebuffer;
+use spaceship::image::{load_png, save_framebuffer};
+use spaceship::mesh::Mesh;
+use spaceship::planets::{Moon, Planet, PlanetType, Ring};
+use spaceship::render::{create_frame_uniforms, create_model_matrix, Renderer, RasterMode};
+use spaceship::shaders::{FragmentShader, Uniforms};
+use spaceship::tonemap::ToneMapping;
+use spaceship::triangle::{CullMode, FrontFace};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 160;
+const TIME: f32 = 1.5;
+
+// Largest per-channel difference for a pixel to still count as matching
+const CHANNEL_TOLERANCE: u8 = 8;
+// Share of pixels allowed outside the tolerance (edge pixels, libm drift)
+const MAX_MISMATCH_RATIO: f64 = 0.002;
+
+fn camera() -> Camera {
+    Camera::new(
+        Vec3::new(0.0, 0.0, 3.2),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        PI / 4.0,
+        0.1,
+        100.0,
+    )
+}
+
+fn render_body(mesh: &Mesh, shader: &dyn FragmentShader, rotation: Vec3, cull_mode: CullMode) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.set_background_color(0x000011);
+    framebuffer.clear();
+
+    let mut frame_uniforms = create_frame_uniforms(&camera(), WIDTH, HEIGHT);
+    // Lit from the upper right so the terminator is in view
+    frame_uniforms.light.position = Vec3::new(6.0, 3.0, 4.0);
+    let uniforms = Uniforms {
+        model_matrix: create_model_matrix(Vec3::zeros(), 1.0, rotation),
+        ..frame_uniforms
+    };
+
+    let mut renderer = Renderer::new(RasterMode::Streaming);
+    renderer.draw(&mut framebuffer, &uniforms, mesh, shader, TIME, cull_mode, FrontFace::Clockwise);
+    renderer.finish(&mut framebuffer);
+    framebuffer.resolve(&ToneMapping::default());
+    framebuffer
+}
+
+fn render_planet(planet_type: PlanetType) -> Framebuffer {
+    let planet = Planet::new(planet_type, Vec3::zeros(), 1.0, 0.0, 0.0, 0.0);
+    // Tilted so the north pole is in view
+    render_body(planet.sphere.get_mesh(), planet.shader.as_ref(), Vec3::new(0.4, 0.8, 0.0), CullMode::Back)
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn diff_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-diff")
+}
+
+fn max_channel_difference(a: u32, b: u32) -> u8 {
+    [16, 8, 0]
+        .iter()
+        .map(|shift| ((a >> shift) as u8).abs_diff((b >> shift) as u8))
+        .max()
+        .unwrap()
+}
+
+fn assert_matches_golden(name: &str, actual: &Framebuffer) {
+    let reference_path = golden_dir().join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        save_framebuffer(actual, &reference_path).unwrap();
+        return;
+    }
+
+    let reference = load_png(&reference_path).unwrap_or_else(|error| {
+        panic!(
+            "missing golden image {} ({}); run UPDATE_GOLDEN=1 cargo test --test golden",
+            reference_path.display(),
+            error
+        )
+    });
+    assert_eq!(
+        (reference.width, reference.height),
+        (actual.width, actual.height),
+        "{}: golden image has a different size",
+        name
+    );
+
+    let mut diff = Framebuffer::new(actual.width, actual.height);
+    let mut mismatches = 0;
+    for (i, (&expected, &got)) in reference.buffer.iter().zip(&actual.buffer).enumerate() {
+        if max_channel_difference(expected, got) > CHANNEL_TOLERANCE {
+            mismatches += 1;
+            diff.buffer[i] = 0xFF0000;
+        } else {
+            diff.buffer[i] = (expected >> 2) & 0x3F3F3F;
+        }
+    }
+
+    let ratio = mismatches as f64 / actual.buffer.len() as f64;
+    if ratio > MAX_MISMATCH_RATIO {
+        std::fs::create_dir_all(diff_dir()).unwrap();
+        let actual_path = diff_dir().join(format!("{}_actual.png", name));
+        let diff_path = diff_dir().join(format!("{}_diff.png", name));
+        save_framebuffer(actual, &actual_path).unwrap();
+        save_framebuffer(&diff, &diff_path).unwrap();
+
+        panic!(
+            "{}: {} pixels ({:.2}%) differ from {} by more than {}; wrote {} and {}",
+            name,
+            mismatches,
+            ratio * 100.0,
+            reference_path.display(),
+            CHANNEL_TOLERANCE,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn star() {
+    assert_matches_golden("star", &render_planet(PlanetType::Star));
+}
+
+#[test]
+fn rocky_planet() {
+    assert_matches_golden("rocky_planet", &render_planet(PlanetType::RockyPlanet));
+}
+
+#[test]
+fn gas_giant() {
+    assert_matches_golden("gas_giant", &render_planet(PlanetType::GasGiant));
+}
+
+#[test]
+fn ice_planet() {
+    assert_matches_golden("ice_planet", &render_planet(PlanetType::IcePlanet));
+}
+
+#[test]
+fn volcanic_planet() {
+    assert_matches_golden("volcanic_planet", &render_planet(PlanetType::VolcanicPlanet));
+}
+
+#[test]
+fn ringed_planet() {
+    assert_matches_golden("ringed_planet", &render_planet(PlanetType::RingedPlanet));
+}
+
+#[test]
+fn moon() {
+    let moon = Moon::new(0.0, 0.0, 1.0);
+    let image = render_body(moon.sphere.get_mesh(), moon.shader.as_ref(), Vec3::new(0.4, 0.8, 0.0), CullMode::Back);
+    assert_matches_golden("moon", &image);
+}
+
+#[test]
+fn ring() {
+    let ring = Ring::new(0.7, 1.2, 64);
+    // Tilted towards the camera, drawn double-sided like in the scene
+    let image = render_body(&ring.mesh, ring.shader.as_ref(), Vec3::new(0.5, 0.3, 0.0), CullMode::None);
+    assert_matches_golden("ring", &image);
+}